@@ -0,0 +1,313 @@
+use crate::{
+    Color, DeviceRequest, Devices, PROTOCOL_VERSION, PersistMode, ProtocolHeader, Selection,
+    SelectorRequest, SelectorResponse, SelectorResult, SerdeJson,
+};
+use tokio::io::AsyncWriteExt;
+
+#[derive(Debug)]
+pub enum SelectorError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Failed(String),
+    Protocol(String),
+    Cancelled,
+}
+
+impl std::fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not run the source selector: {err}"),
+            Self::Json(err) => write!(f, "invalid source selector message: {err}"),
+            Self::Failed(message) => write!(f, "the source selector failed: {message}"),
+            Self::Protocol(message) => write!(f, "source selector protocol error: {message}"),
+            Self::Cancelled => write!(f, "the selection was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+impl From<std::io::Error> for SelectorError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for SelectorError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+pub trait SourceSelector {
+    fn select(
+        &self,
+        request: SelectorRequest,
+    ) -> impl Future<Output = Result<Selection, SelectorError>> + Send;
+
+    fn pick_color(
+        &self,
+        image_path: &str,
+    ) -> impl Future<Output = Result<Color, SelectorError>> + Send;
+
+    fn confirm_devices(
+        &self,
+        request: DeviceRequest,
+    ) -> impl Future<Output = Result<Devices, SelectorError>> + Send;
+}
+
+pub struct ProcessSourceSelector {
+    program: std::path::PathBuf,
+}
+
+impl ProcessSourceSelector {
+    pub fn new(program: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+        }
+    }
+
+    async fn run(&self, args: &[&str], input: &str) -> Result<String, SelectorError> {
+        let mut child = tokio::process::Command::new(&self.program)
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input.as_bytes()).await?;
+        }
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            return Err(SelectorError::Failed(format!(
+                "{} exited with {}: {}",
+                self.program.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        String::from_utf8(output.stdout).map_err(|err| SelectorError::Protocol(err.to_string()))
+    }
+}
+
+impl SourceSelector for ProcessSourceSelector {
+    async fn select(&self, request: SelectorRequest) -> Result<Selection, SelectorError> {
+        let stdout = self.run(&[], &request.to_json()?).await?;
+        if stdout.trim().is_empty() {
+            return Err(SelectorError::Protocol("no answer was returned".into()));
+        }
+        let header = ProtocolHeader::from_json(&stdout)?;
+        if header.version != PROTOCOL_VERSION {
+            return Err(SelectorError::Protocol(format!(
+                "version {} is not supported, expected {PROTOCOL_VERSION}",
+                header.version
+            )));
+        }
+        let selection = match SelectorResponse::from_json(&stdout)?.result {
+            SelectorResult::Selected(selection) => selection,
+            SelectorResult::Cancelled => return Err(SelectorError::Cancelled),
+            SelectorResult::Error { message } => return Err(SelectorError::Failed(message)),
+        };
+        if selection.sources.iter().next().is_none() {
+            return Err(SelectorError::Protocol("no sources were returned".into()));
+        }
+        if !request.cursor_modes.contains(&selection.cursor_mode) {
+            return Err(SelectorError::Protocol(
+                "a cursor mode that was not offered was returned".into(),
+            ));
+        }
+        if selection.persist_mode != PersistMode::None
+            && selection.persist_mode != request.persist_mode
+        {
+            return Err(SelectorError::Protocol(
+                "a persist mode that was not offered was returned".into(),
+            ));
+        }
+        Ok(selection)
+    }
+
+    async fn pick_color(&self, image_path: &str) -> Result<Color, SelectorError> {
+        let stdout = self.run(&["--pick-color", image_path], "").await?;
+        if stdout.trim().is_empty() {
+            return Err(SelectorError::Cancelled);
+        }
+        Ok(Color::from_json(&stdout)?)
+    }
+
+    async fn confirm_devices(&self, request: DeviceRequest) -> Result<Devices, SelectorError> {
+        let stdout = self
+            .run(&["--confirm-devices"], &request.to_json()?)
+            .await?;
+        if stdout.trim().is_empty() {
+            return Err(SelectorError::Cancelled);
+        }
+        let devices = Devices::from_json(&stdout)?;
+        let requested = request.devices;
+        if (devices.keyboard && !requested.keyboard)
+            || (devices.pointer && !requested.pointer)
+            || (devices.touchscreen && !requested.touchscreen)
+        {
+            return Err(SelectorError::Protocol(
+                "a device that was not requested was allowed".into(),
+            ));
+        }
+        if devices == Devices::default() {
+            return Err(SelectorError::Cancelled);
+        }
+        Ok(devices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProcessSourceSelector, SelectorError, SourceSelector};
+    use crate::{
+        CursorMode, DeviceRequest, Devices, PROTOCOL_VERSION, PersistMode, SelectorRequest, Source,
+        SourceTypes,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const SELECTED: &str = r#"{"version":1,"result":{"type":"selected","sources":[{"Monitor":{"monitor_name":"DP-1"}}],"cursor_mode":"hidden","persist_mode":"none"}}"#;
+
+    fn write_stub(script: &str) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "libsourceselector-stub-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&path, format!("#!/bin/sh\ncat > /dev/null\n{script}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn make_request() -> SelectorRequest {
+        SelectorRequest {
+            version: PROTOCOL_VERSION,
+            app_id: "org.example.App".into(),
+            source_types: SourceTypes {
+                monitor: true,
+                window: false,
+                area: false,
+                virtual_monitor: false,
+            },
+            multiple: false,
+            cursor_modes: vec![CursorMode::Hidden],
+            cursor_mode: CursorMode::Hidden,
+            persist_mode: PersistMode::None,
+            sources: vec![Source::Monitor {
+                monitor_name: "DP-1".into(),
+            }]
+            .into(),
+            thumbnails: Vec::new(),
+        }
+    }
+
+    async fn select(script: &str) -> Result<crate::Selection, SelectorError> {
+        let path = write_stub(script);
+        let result = ProcessSourceSelector::new(&path)
+            .select(make_request())
+            .await;
+        _ = std::fs::remove_file(&path);
+        result
+    }
+
+    #[tokio::test]
+    async fn selected() {
+        let selection = select(&format!("printf '%s' '{SELECTED}'")).await.unwrap();
+        assert!(selection.sources.iter().eq([&Source::Monitor {
+            monitor_name: "DP-1".into()
+        }]));
+        assert!(selection.cursor_mode == CursorMode::Hidden);
+    }
+
+    #[tokio::test]
+    async fn version_mismatch() {
+        let result = select(r#"printf '%s' '{"version":2,"result":{"type":"cancelled"}}'"#).await;
+        assert!(matches!(result, Err(SelectorError::Protocol(_))));
+    }
+
+    #[tokio::test]
+    async fn cancelled() {
+        let result = select(r#"printf '%s' '{"version":1,"result":{"type":"cancelled"}}'"#).await;
+        assert!(matches!(result, Err(SelectorError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn non_zero_exit_captures_stderr() {
+        let result = select("echo 'no display' >&2; exit 3").await;
+        match result {
+            Err(SelectorError::Failed(message)) => assert!(message.contains("no display")),
+            _ => panic!("expected a failure"),
+        }
+    }
+
+    #[tokio::test]
+    async fn cursor_mode_not_offered() {
+        let response = SELECTED.replace(r#""cursor_mode":"hidden""#, r#""cursor_mode":"embedded""#);
+        let result = select(&format!("printf '%s' '{response}'")).await;
+        assert!(matches!(result, Err(SelectorError::Protocol(_))));
+    }
+
+    #[tokio::test]
+    async fn persist_mode_not_offered() {
+        let response =
+            SELECTED.replace(r#""persist_mode":"none""#, r#""persist_mode":"persistent""#);
+        let result = select(&format!("printf '%s' '{response}'")).await;
+        assert!(matches!(result, Err(SelectorError::Protocol(_))));
+    }
+
+    #[tokio::test]
+    async fn empty_output() {
+        let result = select("exit 0").await;
+        assert!(matches!(result, Err(SelectorError::Protocol(_))));
+    }
+
+    async fn confirm_devices(script: &str) -> Result<Devices, SelectorError> {
+        let path = write_stub(script);
+        let request = DeviceRequest {
+            app_id: "org.example.App".into(),
+            devices: Devices {
+                keyboard: true,
+                pointer: true,
+                touchscreen: false,
+            },
+        };
+        let result = ProcessSourceSelector::new(&path)
+            .confirm_devices(request)
+            .await;
+        _ = std::fs::remove_file(&path);
+        result
+    }
+
+    #[tokio::test]
+    async fn devices_allowed() {
+        let devices = confirm_devices(
+            r#"printf '%s' '{"keyboard":false,"pointer":true,"touchscreen":false}'"#,
+        )
+        .await
+        .unwrap();
+        assert!(!devices.keyboard && devices.pointer && !devices.touchscreen);
+    }
+
+    #[tokio::test]
+    async fn device_not_requested() {
+        let result =
+            confirm_devices(r#"printf '%s' '{"keyboard":true,"pointer":true,"touchscreen":true}'"#)
+                .await;
+        assert!(matches!(result, Err(SelectorError::Protocol(_))));
+    }
+
+    #[tokio::test]
+    async fn no_device_allowed() {
+        let result = confirm_devices(
+            r#"printf '%s' '{"keyboard":false,"pointer":false,"touchscreen":false}'"#,
+        )
+        .await;
+        assert!(matches!(result, Err(SelectorError::Cancelled)));
+        let result = confirm_devices("exit 0").await;
+        assert!(matches!(result, Err(SelectorError::Cancelled)));
+    }
+}
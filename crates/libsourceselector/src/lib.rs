@@ -1,4 +1,7 @@
-#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[cfg(feature = "client")]
+pub mod client;
+
+#[derive(Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum Source {
     Monitor {
         monitor_name: String,
@@ -8,6 +11,13 @@ pub enum Source {
         window_name: String,
         icon_path: Option<String>,
     },
+    Area {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    },
+    Virtual,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -31,6 +41,108 @@ impl From<Sources> for Vec<Source> {
     }
 }
 
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorMode {
+    #[default]
+    Hidden,
+    Embedded,
+    Metadata,
+}
+
+#[derive(Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PersistMode {
+    None,
+    Transient,
+    Persistent,
+}
+
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct SourceTypes {
+    pub monitor: bool,
+    pub window: bool,
+    pub area: bool,
+    pub virtual_monitor: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ProtocolHeader {
+    pub version: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SelectorRequest {
+    pub version: u32,
+    pub app_id: String,
+    pub source_types: SourceTypes,
+    pub multiple: bool,
+    pub cursor_modes: Vec<CursorMode>,
+    pub cursor_mode: CursorMode,
+    pub persist_mode: PersistMode,
+    pub sources: Sources,
+    #[serde(default)]
+    pub thumbnails: Vec<Thumbnail>,
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Thumbnail {
+    pub source: Source,
+    pub path: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SelectorResponse {
+    pub version: u32,
+    pub result: SelectorResult,
+}
+
+impl SelectorResponse {
+    pub fn new(result: SelectorResult) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            result,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SelectorResult {
+    Selected(Selection),
+    Cancelled,
+    Error { message: String },
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Selection {
+    pub sources: Sources,
+    pub cursor_mode: CursorMode,
+    pub persist_mode: PersistMode,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Devices {
+    pub keyboard: bool,
+    pub pointer: bool,
+    pub touchscreen: bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct DeviceRequest {
+    pub app_id: String,
+    pub devices: Devices,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Color {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+}
+
 pub trait SerdeJson<'de>: serde::Serialize + serde::Deserialize<'de> {
     fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(&self)
@@ -0,0 +1,84 @@
+use libsourceselector::{Color, SerdeJson};
+
+struct ColorPicker {
+    image: image::RgbaImage,
+    texture: Option<egui::TextureHandle>,
+    color: Option<Color>,
+}
+
+impl ColorPicker {
+    fn new(image: image::RgbaImage) -> Self {
+        Self {
+            image,
+            texture: None,
+            color: None,
+        }
+    }
+
+    fn on_image_clicked(&mut self, ctx: &egui::Context, rect: egui::Rect, pos: egui::Pos2) {
+        let x = (pos.x - rect.min.x) / rect.width() * self.image.width() as f32;
+        let y = (pos.y - rect.min.y) / rect.height() * self.image.height() as f32;
+        let x = (x as u32).min(self.image.width() - 1);
+        let y = (y as u32).min(self.image.height() - 1);
+        let [red, green, blue, _] = self.image.get_pixel(x, y).0;
+        self.color = Some(Color {
+            red: f64::from(red) / 255.0,
+            green: f64::from(green) / 255.0,
+            blue: f64::from(blue) / 255.0,
+        });
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+}
+
+impl eframe::App for ColorPicker {
+    fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        let texture = self.texture.get_or_insert_with(|| {
+            let size = [self.image.width() as usize, self.image.height() as usize];
+            let image = egui::ColorImage::from_rgba_unmultiplied(size, self.image.as_raw());
+            ctx.load_texture("screenshot", image, egui::TextureOptions::NEAREST)
+        });
+        let image = egui::widgets::Image::new(&*texture)
+            .shrink_to_fit()
+            .sense(egui::Sense::click());
+        let response = egui::CentralPanel::default()
+            .frame(egui::Frame::NONE)
+            .show(ctx, |ui| {
+                ui.centered_and_justified(|ui| ui.add(image)).inner
+            })
+            .inner;
+        ctx.set_cursor_icon(egui::CursorIcon::Crosshair);
+        if response.clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            self.on_image_clicked(ctx, response.rect, pos);
+        }
+    }
+
+    fn on_exit(&mut self, _: Option<&eframe::glow::Context>) {
+        let Some(color) = &self.color else {
+            eprintln!("Color wasn't picked");
+            return;
+        };
+        match color.to_json() {
+            Ok(json) => println!("{json}"),
+            Err(err) => eprintln!("ColorPicker error: {err}"),
+        }
+    }
+}
+
+pub fn run(image_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let image = image::open(image_path)?.to_rgba8();
+    if image.width() == 0 || image.height() == 0 {
+        return Err(format!("'{image_path}' is empty").into());
+    }
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_fullscreen(true),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "Pick Color",
+        options,
+        Box::new(|_| Ok(Box::new(ColorPicker::new(image)))),
+    )?;
+    Ok(())
+}
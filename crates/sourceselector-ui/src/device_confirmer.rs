@@ -0,0 +1,106 @@
+use libsourceselector::{DeviceRequest, Devices, SerdeJson};
+
+struct DeviceConfirmer {
+    app_id: String,
+    requested: Devices,
+    devices: Devices,
+    allowed: bool,
+}
+
+impl DeviceConfirmer {
+    fn new(request: DeviceRequest) -> Self {
+        Self {
+            app_id: request.app_id,
+            requested: request.devices,
+            devices: request.devices,
+            allowed: false,
+        }
+    }
+
+    fn allow(&mut self, ctx: &egui::Context) {
+        if self.devices == Devices::default() {
+            return;
+        }
+        self.allowed = true;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+}
+
+impl eframe::App for DeviceConfirmer {
+    fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        if ctx.input(|x| x.key_pressed(egui::Key::Escape)) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        egui::TopBottomPanel::bottom("allow-panel").show(ctx, |ui| {
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let has_devices = self.devices != Devices::default();
+                if ui
+                    .add_enabled(has_devices, egui::Button::new("Allow"))
+                    .clicked()
+                {
+                    self.allow(ctx);
+                }
+                if ui.button("Cancel").clicked() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            });
+        });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let app_name = if self.app_id.is_empty() {
+                "An application"
+            } else {
+                &self.app_id
+            };
+            ui.label(
+                egui::RichText::new(format!("{app_name} wants to control this computer")).heading(),
+            );
+            ui.label("Allow it to use:");
+            let devices = [
+                (
+                    self.requested.keyboard,
+                    &mut self.devices.keyboard,
+                    "Keyboard",
+                ),
+                (self.requested.pointer, &mut self.devices.pointer, "Pointer"),
+                (
+                    self.requested.touchscreen,
+                    &mut self.devices.touchscreen,
+                    "Touchscreen",
+                ),
+            ];
+            for (requested, allowed, label) in devices {
+                if requested {
+                    ui.checkbox(allowed, label);
+                }
+            }
+        });
+    }
+
+    fn on_exit(&mut self, _: Option<&eframe::glow::Context>) {
+        if !self.allowed {
+            eprintln!("Devices weren't allowed");
+            return;
+        }
+        match self.devices.to_json() {
+            Ok(json) => println!("{json}"),
+            Err(err) => eprintln!("DeviceConfirmer error: {err}"),
+        }
+    }
+}
+
+pub fn run(request: DeviceRequest) -> Result<(), Box<dyn std::error::Error>> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([480.0, 240.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "Allow Remote Control",
+        options,
+        Box::new(|cc| {
+            crate::egui_fonts::install(&cc.egui_ctx);
+            cc.egui_ctx.set_zoom_factor(1.3);
+            Ok(Box::new(DeviceConfirmer::new(request)))
+        }),
+    )?;
+    Ok(())
+}
@@ -1,22 +1,221 @@
+mod color_picker;
+mod device_confirmer;
 mod egui_fonts;
 
-use libsourceselector::{SerdeJson, Source, Sources};
-use std::cell::Cell;
+use libsourceselector::{
+    CursorMode, DeviceRequest, PROTOCOL_VERSION, PersistMode, ProtocolHeader, Selection,
+    SelectorRequest, SelectorResponse, SelectorResult, SerdeJson, Source, Sources, Thumbnail,
+};
+use std::cell::{Cell, RefCell};
+use std::io::Read;
 
 const ICON_SIZE: f32 = 24.0;
+const THUMBNAIL_SIZE: egui::Vec2 = egui::Vec2 { x: 160.0, y: 90.0 };
+const THUMBNAIL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+const DEFAULT_AREA: (i32, i32, i32, i32) = (0, 0, 1280, 720);
+const SEARCH_ID: &str = "search";
 
 struct SourceSelector {
-    monitor_sources: Sources,
-    window_sources: Sources,
-    selected_source: Cell<Option<Source>>,
+    monitor_sources: Option<Sources>,
+    window_sources: Option<Sources>,
+    virtual_sources: Option<Sources>,
+    area: Option<Cell<(i32, i32, i32, i32)>>,
+    thumbnails: Vec<Thumbnail>,
+    multiple: bool,
+    cursor_modes: Vec<CursorMode>,
+    cursor_mode: Cell<CursorMode>,
+    persist_mode: PersistMode,
+    persist: Cell<bool>,
+    search: RefCell<String>,
+    highlighted_source: RefCell<Option<Source>>,
+    scroll_to_highlighted: Cell<bool>,
+    selected_sources: RefCell<Vec<Source>>,
+    confirmed: Cell<bool>,
 }
 
 impl SourceSelector {
-    fn new(monitor_sources: Sources, window_sources: Sources) -> Self {
+    fn new(request: SelectorRequest) -> Self {
+        let sources: Vec<_> = request.sources.into();
+        let filter_sources = |predicate: fn(&Source) -> bool| {
+            let sources: Vec<_> = sources.iter().filter(|x| predicate(x)).cloned().collect();
+            Sources::from(sources)
+        };
+        let source_types = request.source_types;
         Self {
-            monitor_sources,
-            window_sources,
-            selected_source: Cell::new(None),
+            monitor_sources: source_types
+                .monitor
+                .then(|| filter_sources(|x| matches!(x, Source::Monitor { .. }))),
+            window_sources: source_types
+                .window
+                .then(|| filter_sources(|x| matches!(x, Source::Window { .. }))),
+            virtual_sources: source_types
+                .virtual_monitor
+                .then(|| filter_sources(|x| matches!(x, Source::Virtual))),
+            area: source_types.area.then(|| Cell::new(DEFAULT_AREA)),
+            thumbnails: request.thumbnails,
+            multiple: request.multiple,
+            cursor_modes: request.cursor_modes,
+            cursor_mode: Cell::new(request.cursor_mode),
+            persist_mode: request.persist_mode,
+            persist: Cell::new(request.persist_mode != PersistMode::None),
+            search: RefCell::new(String::new()),
+            highlighted_source: RefCell::new(None),
+            scroll_to_highlighted: Cell::new(false),
+            selected_sources: RefCell::new(Vec::new()),
+            confirmed: Cell::new(false),
+        }
+    }
+
+    fn on_source_clicked(&self, source: &Source) {
+        self.highlight_source(source, false);
+        if self.multiple {
+            self.toggle_source(source);
+        }
+    }
+
+    fn on_source_double_clicked(&self, ctx: &egui::Context, source: &Source) {
+        if !self.multiple {
+            self.highlight_source(source, false);
+            self.confirm(ctx);
+        }
+    }
+
+    fn highlight_source(&self, source: &Source, scroll: bool) {
+        *self.highlighted_source.borrow_mut() = Some(source.to_owned());
+        self.scroll_to_highlighted.set(scroll);
+        if !self.multiple {
+            *self.selected_sources.borrow_mut() = vec![source.to_owned()];
+        }
+    }
+
+    fn move_highlight(&self, offset: isize) {
+        let visible_sources = self.get_visible_sources();
+        if visible_sources.is_empty() {
+            return;
+        }
+        let len = visible_sources.len() as isize;
+        let highlighted_source = self.highlighted_source.borrow().clone();
+        let index = match visible_sources
+            .iter()
+            .position(|x| Some(x) == highlighted_source.as_ref())
+        {
+            Some(index) => (index as isize + offset).rem_euclid(len),
+            None if offset < 0 => len - 1,
+            None => 0,
+        };
+        self.highlight_source(&visible_sources[index as usize], true);
+    }
+
+    fn toggle_source(&self, source: &Source) {
+        let mut selected_sources = self.selected_sources.borrow_mut();
+        if let Some(index) = selected_sources.iter().position(|x| x == source) {
+            selected_sources.remove(index);
+        } else {
+            selected_sources.push(source.to_owned());
+        }
+    }
+
+    fn confirm(&self, ctx: &egui::Context) {
+        if self.selected_sources.borrow().is_empty() {
+            return;
+        }
+        self.confirmed.set(true);
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
+    fn cancel(&self, ctx: &egui::Context) {
+        self.confirmed.set(false);
+        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+    }
+
+    fn handle_keys(&self, ctx: &egui::Context) {
+        let search_id = egui::Id::new(SEARCH_ID);
+        let has_focus = ctx.memory(|x| x.focused().is_none_or(|x| x == search_id));
+        if !has_focus || egui::Popup::is_any_open(ctx) {
+            return;
+        }
+        let consume_key = |modifiers, key| ctx.input_mut(|x| x.consume_key(modifiers, key));
+        if consume_key(egui::Modifiers::SHIFT, egui::Key::Tab)
+            || consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp)
+        {
+            self.move_highlight(-1);
+        }
+        if consume_key(egui::Modifiers::NONE, egui::Key::Tab)
+            || consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown)
+        {
+            self.move_highlight(1);
+        }
+        if self.multiple && consume_key(egui::Modifiers::CTRL, egui::Key::Space) {
+            let highlighted_source = self.highlighted_source.borrow().clone();
+            if let Some(highlighted_source) = highlighted_source {
+                self.toggle_source(&highlighted_source);
+            }
+        }
+        if consume_key(egui::Modifiers::NONE, egui::Key::Enter) {
+            let highlighted_source = self.highlighted_source.borrow().clone();
+            if let Some(highlighted_source) = highlighted_source
+                && self.selected_sources.borrow().is_empty()
+            {
+                self.selected_sources.borrow_mut().push(highlighted_source);
+            }
+            self.confirm(ctx);
+        }
+        if consume_key(egui::Modifiers::NONE, egui::Key::Escape) {
+            self.cancel(ctx);
+        }
+    }
+
+    fn filter_sources(&self, sources: &Sources) -> Sources {
+        let search = self.search.borrow().to_lowercase();
+        let sources: Vec<_> = sources
+            .iter()
+            .filter(|x| get_source_name(x).to_lowercase().contains(&search))
+            .cloned()
+            .collect();
+        sources.into()
+    }
+
+    fn get_visible_sources(&self) -> Vec<Source> {
+        let mut visible_sources = Vec::new();
+        for sources in [
+            &self.monitor_sources,
+            &self.window_sources,
+            &self.virtual_sources,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            visible_sources.extend(Vec::from(self.filter_sources(sources)));
+        }
+        if let Some(area) = &self.area {
+            let (x, y, width, height) = area.get();
+            visible_sources.push(Source::Area {
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+        visible_sources
+    }
+
+    fn add_search_box(&self, ui: &mut egui::Ui) {
+        let mut search = self.search.take();
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut search)
+                .id(egui::Id::new(SEARCH_ID))
+                .hint_text("Search monitors and windows")
+                .lock_focus(true)
+                .desired_width(f32::INFINITY),
+        );
+        self.search.replace(search);
+        if response.changed()
+            && let Some(source) = self.get_visible_sources().first()
+        {
+            self.highlight_source(source, true);
+        }
+        if ui.memory(|x| x.focused().is_none()) {
+            response.request_focus();
         }
     }
 
@@ -27,6 +226,7 @@ impl SourceSelector {
         id_salt: &str,
         sources: &Sources,
     ) {
+        let highlighted_source = self.highlighted_source.borrow().clone();
         egui_extras::TableBuilder::new(ui)
             .id_salt(id_salt)
             .sense(egui::Sense::click())
@@ -34,79 +234,286 @@ impl SourceSelector {
             .body(|mut body| {
                 for source in sources.iter() {
                     body.row(ICON_SIZE, |mut row| {
+                        let is_highlighted = highlighted_source.as_ref() == Some(source);
+                        row.set_selected(self.selected_sources.borrow().contains(source));
+                        row.set_hovered(is_highlighted);
                         let (_, response) = row.col(|ui| {
                             ui.horizontal(|ui| Self::add_source_row_content(ui, source));
                         });
-                        if response.clicked() {
-                            self.selected_source.set(Some(source.to_owned()));
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                        if is_highlighted && self.scroll_to_highlighted.take() {
+                            response.scroll_to_me(None);
+                        }
+                        if response.double_clicked() {
+                            self.on_source_double_clicked(ctx, source);
+                        } else if response.clicked() {
+                            self.on_source_clicked(source);
                         }
                     });
                 }
             });
     }
 
-    fn add_source_row_content(ui: &mut egui::Ui, source: &Source) {
-        let add_row_content = |ui: &mut egui::Ui, name, image| {
-            const IMAGE_SIZE: egui::Vec2 = egui::Vec2 {
-                x: ICON_SIZE,
-                y: ICON_SIZE,
-            };
-            ui.add(egui::widgets::Image::new(image).fit_to_exact_size(IMAGE_SIZE));
-            ui.add(egui::widgets::Label::new(name).selectable(false));
+    fn add_source_grid(
+        &self,
+        ctx: &egui::Context,
+        ui: &mut egui::Ui,
+        id_salt: &str,
+        sources: &Sources,
+    ) {
+        let highlighted_source = self.highlighted_source.borrow().clone();
+        egui::ScrollArea::vertical()
+            .id_salt(id_salt)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for source in sources.iter() {
+                        let is_highlighted = highlighted_source.as_ref() == Some(source);
+                        let is_selected = self.selected_sources.borrow().contains(source);
+                        let response =
+                            self.add_source_card(ui, source, is_selected, is_highlighted);
+                        if is_highlighted && self.scroll_to_highlighted.take() {
+                            response.scroll_to_me(None);
+                        }
+                        if response.double_clicked() {
+                            self.on_source_double_clicked(ctx, source);
+                        } else if response.clicked() {
+                            self.on_source_clicked(source);
+                        }
+                    }
+                });
+            });
+    }
+
+    fn add_source_card(
+        &self,
+        ui: &mut egui::Ui,
+        source: &Source,
+        is_selected: bool,
+        is_highlighted: bool,
+    ) -> egui::Response {
+        let visuals = ui.visuals();
+        let fill = if is_selected {
+            visuals.selection.bg_fill
+        } else {
+            visuals.faint_bg_color
+        };
+        let stroke = if is_highlighted {
+            visuals.selection.stroke
+        } else {
+            egui::Stroke::NONE
         };
-        match source {
-            Source::Monitor { monitor_name } => {
-                let image = egui::include_image!("icons/video-display-symbolic.svg");
-                add_row_content(ui, monitor_name, image);
+        let frame = egui::Frame::new()
+            .fill(fill)
+            .stroke(stroke)
+            .corner_radius(4.0)
+            .inner_margin(4.0);
+        frame
+            .show(ui, |ui| {
+                ui.set_width(THUMBNAIL_SIZE.x);
+                ui.vertical_centered(|ui| {
+                    let placeholder = match source {
+                        Source::Window { .. } => {
+                            egui::include_image!("icons/preferences-system-symbolic.svg")
+                        }
+                        _ => egui::include_image!("icons/video-display-symbolic.svg"),
+                    };
+                    let image = match self.get_thumbnail_path(source) {
+                        Some(path) if std::path::Path::new(path).exists() => {
+                            egui::ImageSource::Uri(format!("file://{path}").into())
+                        }
+                        Some(_) => {
+                            ui.ctx().request_repaint_after(THUMBNAIL_POLL_INTERVAL);
+                            placeholder
+                        }
+                        None => placeholder,
+                    };
+                    ui.add(egui::widgets::Image::new(image).fit_to_exact_size(THUMBNAIL_SIZE));
+                    ui.horizontal(|ui| Self::add_source_row_content(ui, source));
+                });
+            })
+            .response
+            .interact(egui::Sense::click())
+    }
+
+    fn get_thumbnail_path(&self, source: &Source) -> Option<&str> {
+        self.thumbnails
+            .iter()
+            .find(|x| x.source == *source)
+            .map(|x| x.path.as_str())
+    }
+
+    fn add_area_editor(
+        &self,
+        ctx: &egui::Context,
+        ui: &mut egui::Ui,
+        area: &Cell<(i32, i32, i32, i32)>,
+    ) {
+        let (mut x, mut y, mut width, mut height) = area.get();
+        let old_source = Source::Area {
+            x,
+            y,
+            width,
+            height,
+        };
+        ui.horizontal(|ui| {
+            ui.label("X");
+            ui.add(egui::DragValue::new(&mut x));
+            ui.label("Y");
+            ui.add(egui::DragValue::new(&mut y));
+            ui.label("Width");
+            ui.add(egui::DragValue::new(&mut width).range(1..=i32::MAX));
+            ui.label("Height");
+            ui.add(egui::DragValue::new(&mut height).range(1..=i32::MAX));
+        });
+        area.set((x, y, width, height));
+        let source = Source::Area {
+            x,
+            y,
+            width,
+            height,
+        };
+        if source != old_source {
+            for selected_source in self.selected_sources.borrow_mut().iter_mut() {
+                if *selected_source == old_source {
+                    *selected_source = source.clone();
+                }
             }
-            Source::Window {
-                window_name,
-                icon_path,
-                ..
-            } => {
-                let image = if let Some(icon_path) = icon_path {
-                    egui::ImageSource::Uri(icon_path.into())
-                } else {
-                    egui::include_image!("icons/preferences-system-symbolic.svg")
-                };
-                add_row_content(ui, window_name, image);
+            let mut highlighted_source = self.highlighted_source.borrow_mut();
+            if highlighted_source.as_ref() == Some(&old_source) {
+                *highlighted_source = Some(source.clone());
             }
         }
+        self.add_source_table(ctx, ui, "area-table", &vec![source].into());
+    }
+
+    fn add_cursor_mode_selector(&self, ui: &mut egui::Ui) {
+        let get_label = |cursor_mode| match cursor_mode {
+            CursorMode::Hidden => "Hide pointer",
+            CursorMode::Embedded => "Show pointer",
+            CursorMode::Metadata => "Share pointer position",
+        };
+        let mut cursor_mode = self.cursor_mode.get();
+        egui::ComboBox::from_id_salt("cursor-mode")
+            .selected_text(get_label(cursor_mode))
+            .show_ui(ui, |ui| {
+                for &x in &self.cursor_modes {
+                    ui.selectable_value(&mut cursor_mode, x, get_label(x));
+                }
+            });
+        self.cursor_mode.set(cursor_mode);
+    }
+
+    fn add_source_row_content(ui: &mut egui::Ui, source: &Source) {
+        const IMAGE_SIZE: egui::Vec2 = egui::Vec2 {
+            x: ICON_SIZE,
+            y: ICON_SIZE,
+        };
+        let image = match source {
+            Source::Window {
+                icon_path: Some(icon_path),
+                ..
+            } => egui::ImageSource::Uri(icon_path.into()),
+            Source::Window { .. } => egui::include_image!("icons/preferences-system-symbolic.svg"),
+            _ => egui::include_image!("icons/video-display-symbolic.svg"),
+        };
+        ui.add(egui::widgets::Image::new(image).fit_to_exact_size(IMAGE_SIZE));
+        ui.add(
+            egui::widgets::Label::new(get_source_name(source))
+                .selectable(false)
+                .truncate(),
+        );
     }
 }
 
 impl eframe::App for SourceSelector {
     fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        self.handle_keys(ctx);
+        egui::TopBottomPanel::top("search-panel").show(ctx, |ui| {
+            self.add_search_box(ui);
+        });
+        egui::TopBottomPanel::bottom("share-panel").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if self.cursor_modes.len() > 1 {
+                    self.add_cursor_mode_selector(ui);
+                }
+                if self.persist_mode != PersistMode::None {
+                    let mut persist = self.persist.get();
+                    ui.checkbox(&mut persist, "Remember this selection");
+                    self.persist.set(persist);
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let has_selection = !self.selected_sources.borrow().is_empty();
+                    if ui
+                        .add_enabled(has_selection, egui::Button::new("Share"))
+                        .clicked()
+                    {
+                        self.confirm(ctx);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.cancel(ctx);
+                    }
+                });
+            });
+        });
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.vertical_centered(|ui| {
-                ui.label(egui::RichText::new("Monitors").heading());
-                self.add_source_table(ctx, ui, "monitor-table", &self.monitor_sources);
-                ui.separator();
-                ui.label(egui::RichText::new("Windows").heading());
-                self.add_source_table(ctx, ui, "window-table", &self.window_sources);
+                let sections = [
+                    ("Monitors", "monitor-table", &self.monitor_sources),
+                    ("Windows", "window-table", &self.window_sources),
+                    ("Virtual Displays", "virtual-table", &self.virtual_sources),
+                ];
+                let is_searching = !self.search.borrow().is_empty();
+                let sections = sections
+                    .into_iter()
+                    .filter_map(|(heading, id_salt, sources)| {
+                        Some((heading, id_salt, self.filter_sources(sources.as_ref()?)))
+                    })
+                    .filter(|(_, _, sources)| !is_searching || sources.iter().next().is_some());
+                let mut is_first_section = true;
+                let mut add_section_heading = |ui: &mut egui::Ui, heading: &str| {
+                    if !std::mem::take(&mut is_first_section) {
+                        ui.separator();
+                    }
+                    ui.label(egui::RichText::new(heading).heading());
+                };
+                for (heading, id_salt, sources) in sections {
+                    add_section_heading(ui, heading);
+                    if sources.iter().any(|x| self.get_thumbnail_path(x).is_some()) {
+                        self.add_source_grid(ctx, ui, id_salt, &sources);
+                    } else {
+                        self.add_source_table(ctx, ui, id_salt, &sources);
+                    }
+                }
+                if let Some(area) = &self.area {
+                    add_section_heading(ui, "Area");
+                    self.add_area_editor(ctx, ui, area);
+                }
             });
         });
     }
 
     fn on_exit(&mut self, _: Option<&eframe::glow::Context>) {
-        let Some(selected_source) = self.selected_source.take() else {
-            eprintln!("Source wasn't selected");
-            return;
+        let selected_sources = self.selected_sources.take();
+        let result = if !self.confirmed.get() || selected_sources.is_empty() {
+            SelectorResult::Cancelled
+        } else {
+            SelectorResult::Selected(Selection {
+                sources: selected_sources.into(),
+                cursor_mode: self.cursor_mode.get(),
+                persist_mode: if self.persist.get() {
+                    self.persist_mode
+                } else {
+                    PersistMode::None
+                },
+            })
         };
-        match selected_source.to_json() {
-            Ok(json) => println!("{json}"),
-            Err(err) => eprintln!("SourceSelector error: {err}"),
-        }
+        print_response(result);
     }
 }
 
-fn run(
-    monitor_sources: Sources,
-    window_sources: Sources,
-) -> Result<(), Box<dyn std::error::Error>> {
+fn run(request: SelectorRequest) -> Result<(), Box<dyn std::error::Error>> {
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([500.0, 500.0]),
+        viewport: egui::ViewportBuilder::default().with_inner_size([720.0, 560.0]),
         ..Default::default()
     };
     eframe::run_native(
@@ -116,30 +523,80 @@ fn run(
             egui_extras::install_image_loaders(&cc.egui_ctx);
             egui_fonts::install(&cc.egui_ctx);
             cc.egui_ctx.set_zoom_factor(1.3);
-            Ok(Box::new(SourceSelector::new(
-                monitor_sources,
-                window_sources,
-            )))
+            Ok(Box::new(SourceSelector::new(request)))
         }),
     )?;
     Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<_> = std::env::args().collect();
-    if args.len() == 3 {
-        let monitor_sources = &args[1];
-        let window_sources = &args[2];
-        run(
-            Sources::from_json(monitor_sources)?,
-            Sources::from_json(window_sources)?,
-        )
-    } else {
+    let mut args: Vec<_> = std::env::args().collect();
+    if take_flag(&mut args, "--pick-color") && args.len() == 2 {
+        return color_picker::run(&args[1]);
+    }
+    if take_flag(&mut args, "--confirm-devices") && args.len() == 1 {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        return device_confirmer::run(DeviceRequest::from_json(&input)?);
+    }
+    if args.len() != 1 {
         let progname = args.first().and_then(|x| x.split('/').next_back());
-        eprintln!(
-            "Usage: {} [MONITOR SOURCES] [WINDOW SOURCES]",
-            progname.unwrap_or(env!("CARGO_PKG_NAME"))
-        );
-        Ok(())
+        let progname = progname.unwrap_or(env!("CARGO_PKG_NAME"));
+        eprintln!("Usage: {progname} < [REQUEST]");
+        eprintln!("       {progname} --pick-color [IMAGE]");
+        eprintln!("       {progname} --confirm-devices < [REQUEST]");
+        return Ok(());
+    }
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    let request = match ProtocolHeader::from_json(&input) {
+        Ok(header) if header.version != PROTOCOL_VERSION => {
+            Err(format!("unsupported protocol version: {}", header.version))
+        }
+        _ => SelectorRequest::from_json(&input).map_err(|err| format!("invalid request: {err}")),
+    };
+    let request = match request {
+        Ok(request) => request,
+        Err(message) => {
+            print_response(SelectorResult::Error { message });
+            return Ok(());
+        }
+    };
+    if let Err(err) = run(request) {
+        print_response(SelectorResult::Error {
+            message: err.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn print_response(result: SelectorResult) {
+    match SelectorResponse::new(result).to_json() {
+        Ok(json) => println!("{json}"),
+        Err(err) => eprintln!("SourceSelector error: {err}"),
+    }
+}
+
+fn get_source_name(source: &Source) -> String {
+    match source {
+        Source::Monitor { monitor_name } => monitor_name.clone(),
+        Source::Window { window_name, .. } => window_name.clone(),
+        Source::Area {
+            x,
+            y,
+            width,
+            height,
+        } => format!("{width}x{height} at ({x}, {y})"),
+        Source::Virtual => "New virtual display".into(),
+    }
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|x| x == flag) {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
     }
 }
@@ -0,0 +1,100 @@
+use crate::portal_impl;
+use futures_util::StreamExt;
+use std::time::Duration;
+
+const SCREENCAST_NAME: &str = "org.cinnamon.Muffin.ScreenCast";
+const REMOTE_DESKTOP_NAME: &str = "org.cinnamon.Muffin.RemoteDesktop";
+const DISPLAY_CONFIG_NAME: &str = "org.cinnamon.Muffin.DisplayConfig";
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+pub async fn setup(
+    connection: &zbus::Connection,
+    screencast_ctx: portal_impl::ScreenCast,
+    remote_desktop_ctx: portal_impl::RemoteDesktop,
+    screenshot_ctx: portal_impl::Screenshot,
+) -> zbus::Result<()> {
+    let dbus_proxy = zbus::fdo::DBusProxy::new(connection).await?;
+    let mut watcher = CompositorWatcher {
+        dbus_proxy,
+        screencast_ctx,
+        remote_desktop_ctx,
+        screenshot_ctx,
+        backoff: MIN_BACKOFF,
+    };
+
+    tokio::spawn(async move { watcher.supervise().await });
+
+    Ok(())
+}
+
+struct CompositorWatcher {
+    dbus_proxy: zbus::fdo::DBusProxy<'static>,
+    screencast_ctx: portal_impl::ScreenCast,
+    remote_desktop_ctx: portal_impl::RemoteDesktop,
+    screenshot_ctx: portal_impl::Screenshot,
+    backoff: Duration,
+}
+
+impl CompositorWatcher {
+    async fn supervise(&mut self) -> ! {
+        loop {
+            match self.watch().await {
+                Ok(()) => log::warn!("org.freedesktop.DBus - NameOwnerChanged(): unavailable"),
+                Err(err) => log::warn!("org.freedesktop.DBus - NameOwnerChanged(): {err}"),
+            }
+            log::warn!("Resubscribing to NameOwnerChanged in {:?}", self.backoff);
+            tokio::time::sleep(self.backoff).await;
+            self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    async fn watch(&mut self) -> zbus::Result<()> {
+        let mut streams = Vec::new();
+        for name in [SCREENCAST_NAME, REMOTE_DESKTOP_NAME, DISPLAY_CONFIG_NAME] {
+            streams.push(
+                self.dbus_proxy
+                    .receive_name_owner_changed_with_args(&[(0, name)])
+                    .await?,
+            );
+        }
+        let mut stream = futures_util::stream::select_all(streams);
+        log::info!("Watching the compositor for restarts");
+        self.backoff = MIN_BACKOFF;
+        while let Some(signal) = stream.next().await {
+            let args = match signal.args() {
+                Ok(args) => args,
+                Err(err) => {
+                    log::error!("org.freedesktop.DBus - NameOwnerChanged(): {err}");
+                    continue;
+                }
+            };
+            let name = args.name().as_str();
+            if args.old_owner().is_some() {
+                log::warn!("{name} has left the bus, closing all sessions");
+                self.screencast_ctx.close_all_sessions().await;
+            }
+            if args.new_owner().is_some() {
+                log::info!("{name} has appeared on the bus");
+                self.on_name_appeared(name).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_name_appeared(&self, name: &str) {
+        let result = match name {
+            SCREENCAST_NAME => {
+                let screencast = self.screencast_ctx.on_compositor_appeared().await;
+                let screenshot = self.screenshot_ctx.on_compositor_appeared().await;
+                screencast.and(screenshot)
+            }
+            REMOTE_DESKTOP_NAME => self.remote_desktop_ctx.on_compositor_appeared().await,
+            _ => Ok(()),
+        };
+        if let Err(err) = result {
+            log::error!("Could not reconnect to {name}: {err}");
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use libsourceselector::CursorMode;
+use std::{
+    sync::{Arc, LazyLock, RwLock},
+    time::{Duration, SystemTime},
+};
+
+const CONFIG_FILE: &str = "xdg-desktop-portal-screencast/config.toml";
+const SYSTEM_CONFIG_DIR: &str = "/etc";
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+static CONFIG: LazyLock<RwLock<Arc<Config>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(load().unwrap_or_default())));
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub selector: SelectorConfig,
+    pub timeouts: TimeoutsConfig,
+    pub screencast: ScreenCastConfig,
+    pub icons: IconsConfig,
+    pub log: LogConfig,
+    pub policy: Vec<PolicyRule>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SelectorConfig {
+    pub command: Option<String>,
+    pub thumbnails: bool,
+}
+
+impl Default for SelectorConfig {
+    fn default() -> Self {
+        Self {
+            command: None,
+            thumbnails: true,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutsConfig {
+    pub pipewire_stream_ms: u64,
+    pub shutdown_ms: u64,
+}
+
+impl Default for TimeoutsConfig {
+    fn default() -> Self {
+        Self {
+            pipewire_stream_ms: 1000,
+            shutdown_ms: 5000,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScreenCastConfig {
+    pub default_cursor_mode: CursorMode,
+}
+
+#[derive(serde::Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct IconsConfig {
+    pub search_paths: Option<Vec<String>>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct PolicyRule {
+    pub app_id: String,
+    #[serde(flatten)]
+    pub action: PolicyAction,
+}
+
+#[derive(serde::Deserialize, Clone)]
+#[serde(tag = "action", rename_all = "lowercase", deny_unknown_fields)]
+pub enum PolicyAction {
+    Deny,
+    Ask,
+    Select {
+        monitor: Option<String>,
+        window: Option<String>,
+    },
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub level: log::LevelFilter,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: log::LevelFilter::Error,
+        }
+    }
+}
+
+impl Config {
+    fn validate(&self) -> Result<(), String> {
+        if self.screencast.default_cursor_mode == CursorMode::Embedded {
+            return Err("screencast.default_cursor_mode \"embedded\" is not supported".into());
+        }
+        Ok(())
+    }
+}
+
+pub fn get() -> Arc<Config> {
+    CONFIG.read().unwrap().clone()
+}
+
+pub fn reload() {
+    let Some(config) = load() else {
+        log::error!("Keeping the previous configuration");
+        return;
+    };
+    apply_log_level(&config);
+    *CONFIG.write().unwrap() = Arc::new(config);
+    log::info!("Configuration reloaded");
+}
+
+pub fn setup() {
+    apply_log_level(&get());
+
+    tokio::spawn(async move {
+        let mut last_modified = get_modified_times();
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let modified = get_modified_times();
+            if modified != last_modified {
+                last_modified = modified;
+                reload();
+            }
+        }
+    });
+}
+
+fn load() -> Option<Config> {
+    for path in get_config_paths() {
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                log::error!("Could not read '{path}': {err}");
+                return None;
+            }
+        };
+        let config: Config = match toml::from_str(&content) {
+            Ok(config) => config,
+            Err(err) => {
+                log::error!("Could not parse '{path}': {err}");
+                return None;
+            }
+        };
+        if let Err(err) = config.validate() {
+            log::error!("Invalid configuration in '{path}': {err}");
+            return None;
+        }
+        return Some(config);
+    }
+    Some(Config::default())
+}
+
+fn apply_log_level(config: &Config) {
+    if std::env::var_os("RUST_LOG").is_none() {
+        log::set_max_level(config.log.level);
+    }
+}
+
+fn get_modified_times() -> Vec<Option<SystemTime>> {
+    get_config_paths()
+        .iter()
+        .map(|x| std::fs::metadata(x).and_then(|x| x.modified()).ok())
+        .collect()
+}
+
+fn get_config_paths() -> Vec<String> {
+    let mut paths = Vec::new();
+    match get_xdg_dir("XDG_CONFIG_HOME", ".config") {
+        Ok(xdg_config_home) => paths.push(format!("{xdg_config_home}/{CONFIG_FILE}")),
+        Err(err) => log::warn!("Could not determine the $XDG_CONFIG_HOME directory: {err}"),
+    }
+    paths.push(format!("{SYSTEM_CONFIG_DIR}/{CONFIG_FILE}"));
+    paths
+}
+
+pub fn get_xdg_dir(var: &str, fallback: &str) -> Result<String, Box<dyn std::error::Error>> {
+    match std::env::var(var) {
+        Ok(xdg_dir) => Ok(xdg_dir),
+        Err(std::env::VarError::NotPresent) => match std::env::var("HOME") {
+            Ok(home) => Ok(format!("{home}/{fallback}")),
+            Err(err) => Err(format!("Failed to lookup user's home directory: {err}").into()),
+        },
+        Err(err) => Err(format!("Invalid ${var} variable: {err}").into()),
+    }
+}
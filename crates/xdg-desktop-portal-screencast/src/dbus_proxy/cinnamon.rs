@@ -1,2 +1,4 @@
 mod portal_handlers;
+mod screenshot;
 pub use portal_handlers::PortalHandlersProxy as PortalHandlers;
+pub use screenshot::ScreenshotProxy as Screenshot;
@@ -0,0 +1,29 @@
+#[zbus::proxy(
+    interface = "org.Cinnamon",
+    default_service = "org.Cinnamon",
+    default_path = "/org/Cinnamon",
+    gen_blocking = false
+)]
+pub trait Screenshot {
+    fn screenshot(&self, include_cursor: bool, flash: bool, filename: &str) -> zbus::Result<()>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn screenshot_area(
+        &self,
+        include_cursor: bool,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        flash: bool,
+        filename: &str,
+    ) -> zbus::Result<()>;
+
+    fn screenshot_window(
+        &self,
+        include_frame: bool,
+        include_cursor: bool,
+        flash: bool,
+        filename: &str,
+    ) -> zbus::Result<()>;
+}
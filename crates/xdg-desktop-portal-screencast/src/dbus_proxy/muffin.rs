@@ -1,10 +1,14 @@
 mod display_config;
+mod remote_desktop;
+mod remote_desktop_session;
 mod screencast;
 mod screencast_session;
 mod screencast_stream;
 mod window;
 
 pub use display_config::DisplayConfigProxy as DisplayConfig;
+pub use remote_desktop::RemoteDesktopProxy as RemoteDesktop;
+pub use remote_desktop_session::SessionProxy as RemoteDesktopSession;
 pub use screencast::ScreenCastProxy as ScreenCast;
 pub use screencast_session::SessionProxy as ScreenCastSession;
 pub use screencast_stream::StreamProxy as ScreenCastStream;
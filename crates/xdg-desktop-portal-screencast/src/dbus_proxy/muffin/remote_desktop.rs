@@ -0,0 +1,17 @@
+use super::remote_desktop_session::*;
+
+#[zbus::proxy(
+    interface = "org.cinnamon.Muffin.RemoteDesktop",
+    default_service = "org.cinnamon.Muffin.RemoteDesktop",
+    default_path = "/org/cinnamon/Muffin/RemoteDesktop",
+    gen_blocking = false
+)]
+pub trait RemoteDesktop {
+    #[zbus(object = "Session")]
+    fn create_session(&self);
+
+    #[zbus(property)]
+    fn supported_device_types(&self) -> zbus::Result<u32>;
+    #[zbus(property)]
+    fn version(&self) -> zbus::Result<i32>;
+}
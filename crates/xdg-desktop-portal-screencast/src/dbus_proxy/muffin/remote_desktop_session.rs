@@ -0,0 +1,25 @@
+#[zbus::proxy(
+    interface = "org.cinnamon.Muffin.RemoteDesktop.Session",
+    default_service = "org.cinnamon.Muffin.RemoteDesktop",
+    gen_blocking = false
+)]
+pub trait Session {
+    fn start(&self) -> zbus::Result<()>;
+    fn stop(&self) -> zbus::Result<()>;
+    fn notify_keyboard_keycode(&self, keycode: u32, state: bool) -> zbus::Result<()>;
+    fn notify_keyboard_keysym(&self, keysym: u32, state: bool) -> zbus::Result<()>;
+    fn notify_pointer_axis(&self, dx: f64, dy: f64, flags: u32) -> zbus::Result<()>;
+    fn notify_pointer_axis_discrete(&self, axis: u32, steps: i32) -> zbus::Result<()>;
+    fn notify_pointer_button(&self, button: i32, state: bool) -> zbus::Result<()>;
+    fn notify_pointer_motion_absolute(&self, stream: &str, x: f64, y: f64) -> zbus::Result<()>;
+    fn notify_pointer_motion_relative(&self, dx: f64, dy: f64) -> zbus::Result<()>;
+    fn notify_touch_down(&self, stream: &str, slot: u32, x: f64, y: f64) -> zbus::Result<()>;
+    fn notify_touch_motion(&self, stream: &str, slot: u32, x: f64, y: f64) -> zbus::Result<()>;
+    fn notify_touch_up(&self, slot: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn closed(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn session_id(&self) -> zbus::Result<String>;
+}
@@ -17,6 +17,20 @@ pub trait Session {
         &self,
         properties: std::collections::HashMap<&str, &zbus::zvariant::Value<'_>>,
     );
+    #[zbus(object = "Stream")]
+    fn record_area(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        properties: std::collections::HashMap<&str, &zbus::zvariant::Value<'_>>,
+    );
+    #[zbus(object = "Stream")]
+    fn record_virtual(
+        &self,
+        properties: std::collections::HashMap<&str, &zbus::zvariant::Value<'_>>,
+    );
     fn start(&self) -> zbus::Result<()>;
     fn stop(&self) -> zbus::Result<()>;
 
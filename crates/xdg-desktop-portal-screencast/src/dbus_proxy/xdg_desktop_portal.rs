@@ -1,2 +1,3 @@
-mod session;
-pub use session::SessionProxy as Session;
+mod permission_store;
+pub use permission_store::Changed as PermissionStoreChanged;
+pub use permission_store::PermissionStoreProxy as PermissionStore;
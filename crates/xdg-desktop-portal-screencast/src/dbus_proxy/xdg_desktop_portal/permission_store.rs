@@ -0,0 +1,41 @@
+#[zbus::proxy(
+    interface = "org.freedesktop.impl.portal.PermissionStore",
+    default_service = "org.freedesktop.impl.portal.PermissionStore",
+    default_path = "/org/freedesktop/impl/portal/PermissionStore",
+    gen_blocking = false
+)]
+pub trait PermissionStore {
+    fn delete(&self, table: &str, id: &str) -> zbus::Result<()>;
+    fn delete_permission(&self, table: &str, id: &str, app: &str) -> zbus::Result<()>;
+    fn list(&self, table: &str) -> zbus::Result<Vec<String>>;
+    #[allow(clippy::type_complexity)]
+    fn lookup(
+        &self,
+        table: &str,
+        id: &str,
+    ) -> zbus::Result<(
+        std::collections::HashMap<String, Vec<String>>,
+        zbus::zvariant::OwnedValue,
+    )>;
+    fn set_permission(
+        &self,
+        table: &str,
+        create: bool,
+        id: &str,
+        app: &str,
+        permissions: &[&str],
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn changed(
+        &self,
+        table: &str,
+        id: &str,
+        deleted: bool,
+        data: zbus::zvariant::Value<'_>,
+        permissions: std::collections::HashMap<&str, Vec<&str>>,
+    ) -> zbus::Result<()>;
+
+    #[zbus(property, name = "version")]
+    fn version(&self) -> zbus::Result<u32>;
+}
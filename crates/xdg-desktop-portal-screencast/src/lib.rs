@@ -1,13 +1,18 @@
+mod compositor_watcher;
+mod config;
 mod dbus_proxy;
+mod permission_watcher;
 mod portal_impl;
 mod running_app_watcher;
-mod sigint_handler;
+mod signal_handler;
 
-use std::sync::atomic::Ordering;
+const BUS_NAME: &str = "org.freedesktop.impl.portal.desktop.screencast";
 
 pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut signal_handler = signal_handler::SignalHandler::setup()?;
+    config::setup();
     let connection = zbus::connection::Builder::session()?
-        .name("org.freedesktop.impl.portal.desktop.screencast")?
+        .name(BUS_NAME)?
         .build()
         .await?;
     let screencast_ctx = portal_impl::ScreenCast::new(connection.clone()).await?;
@@ -15,13 +20,42 @@ pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
         .object_server()
         .at("/org/freedesktop/portal/desktop", screencast_ctx.clone())
         .await?;
+    let remote_desktop_ctx =
+        portal_impl::RemoteDesktop::new(connection.clone(), screencast_ctx.clone()).await?;
+    connection
+        .object_server()
+        .at(
+            "/org/freedesktop/portal/desktop",
+            remote_desktop_ctx.clone(),
+        )
+        .await?;
+    let screenshot_ctx = portal_impl::Screenshot::new(connection.clone()).await?;
+    connection
+        .object_server()
+        .at("/org/freedesktop/portal/desktop", screenshot_ctx.clone())
+        .await?;
 
-    running_app_watcher::setup(&connection, screencast_ctx).await?;
+    compositor_watcher::setup(
+        &connection,
+        screencast_ctx.clone(),
+        remote_desktop_ctx,
+        screenshot_ctx,
+    )
+    .await?;
+    permission_watcher::setup(&connection, screencast_ctx.clone()).await?;
+    running_app_watcher::setup(&connection, screencast_ctx.clone()).await?;
 
-    let sigint_caught = sigint_handler::setup();
+    signal_handler.wait_for_shutdown().await;
 
-    while !sigint_caught.load(Ordering::Relaxed) {
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    let shutdown = async {
+        screencast_ctx.close_all_sessions().await;
+        connection.release_name(BUS_NAME).await
+    };
+    let shutdown_timeout = std::time::Duration::from_millis(config::get().timeouts.shutdown_ms);
+    match tokio::time::timeout(shutdown_timeout, shutdown).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(err)) => log::error!("Could not release {BUS_NAME}: {err}"),
+        Err(_) => log::warn!("Shutdown did not finish within {shutdown_timeout:?}"),
     }
 
     Ok(())
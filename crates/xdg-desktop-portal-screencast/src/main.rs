@@ -1,6 +1,10 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let format_target = std::env::var("RUST_LOG_FORMAT_TARGET").is_ok_and(|x| x == "1");
-    env_logger::builder().format_target(format_target).init();
+    let mut builder = env_logger::builder();
+    if std::env::var_os("RUST_LOG").is_none() {
+        builder.filter_level(log::LevelFilter::Trace);
+    }
+    builder.format_target(format_target).init();
 
     tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -0,0 +1,33 @@
+use crate::portal_impl;
+use futures_util::StreamExt;
+use std::collections::HashSet;
+
+pub async fn setup(
+    connection: &zbus::Connection,
+    screencast_ctx: portal_impl::ScreenCast,
+) -> zbus::Result<()> {
+    let permission_store = portal_impl::PermissionStore::new(connection).await?;
+    let mut stream = permission_store.receive_granted_apps_changed().await?;
+    let mut last_granted_apps = match permission_store.get_granted_apps().await {
+        Ok(granted_apps) => granted_apps,
+        Err(err) => {
+            log::warn!("Could not look up the screencast permissions: {err}");
+            HashSet::new()
+        }
+    };
+
+    tokio::spawn(async move {
+        while let Some(granted_apps) = stream.next().await {
+            for app_id in last_granted_apps.difference(&granted_apps) {
+                log::info!("The screencast permission of '{app_id}' was revoked");
+                screencast_ctx.on_permission_revoked(app_id).await;
+            }
+            last_granted_apps = granted_apps;
+        }
+        log::warn!(
+            "org.freedesktop.impl.portal.PermissionStore - Changed(): The signal stream ended"
+        );
+    });
+
+    Ok(())
+}
@@ -1,7 +1,21 @@
+mod error;
+mod permission_store;
+mod policy;
+mod remote_desktop;
+mod request;
+mod restore_store;
 mod screencast;
 mod screencast_session;
 mod screencast_stream;
+mod screenshot;
+mod session;
+mod source_selector;
 
+pub use permission_store::PermissionStore;
+pub use remote_desktop::RemoteDesktop;
+pub use request::Request;
 pub use screencast::ScreenCast;
 pub use screencast_session::ScreenCastSession;
 pub use screencast_stream::ScreenCastStream;
+pub use screenshot::Screenshot;
+pub use session::Session;
@@ -0,0 +1,87 @@
+use libsourceselector::client::SelectorError;
+use zbus::zvariant;
+
+#[derive(Debug)]
+pub enum Error {
+    Cancelled,
+    Closed,
+    Denied(String),
+    InvalidArgument(String),
+    NotFound(String),
+    Failed(String),
+    Selector(SelectorError),
+    DBus(zbus::Error),
+    Io(std::io::Error),
+}
+
+impl Error {
+    pub fn response(&self) -> u32 {
+        match self {
+            Self::Cancelled | Self::Closed => 1,
+            _ => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cancelled => write!(f, "the request was cancelled"),
+            Self::Closed => write!(f, "the request was closed"),
+            Self::Denied(message)
+            | Self::InvalidArgument(message)
+            | Self::NotFound(message)
+            | Self::Failed(message) => write!(f, "{message}"),
+            Self::Selector(err) => write!(f, "{err}"),
+            Self::DBus(err) => write!(f, "{err}"),
+            Self::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<SelectorError> for Error {
+    fn from(value: SelectorError) -> Self {
+        match value {
+            SelectorError::Cancelled => Self::Cancelled,
+            err => Self::Selector(err),
+        }
+    }
+}
+
+impl From<zbus::Error> for Error {
+    fn from(value: zbus::Error) -> Self {
+        Self::DBus(value)
+    }
+}
+
+impl From<zbus::fdo::Error> for Error {
+    fn from(value: zbus::fdo::Error) -> Self {
+        Self::DBus(value.into())
+    }
+}
+
+impl From<zvariant::Error> for Error {
+    fn from(value: zvariant::Error) -> Self {
+        Self::DBus(value.into())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<Error> for zbus::fdo::Error {
+    fn from(value: Error) -> Self {
+        match value {
+            Error::Denied(message) => Self::AccessDenied(message),
+            Error::InvalidArgument(message) => Self::InvalidArgs(message),
+            Error::NotFound(message) => Self::UnknownObject(message),
+            Error::DBus(err) => err.into(),
+            err => Self::Failed(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::dbus_proxy;
+use futures_util::StreamExt;
+use std::collections::{HashMap, HashSet};
+
+const TABLE: &str = "screencast";
+const ID: &str = "screencast";
+
+const PERMISSION_YES: &str = "yes";
+const PERMISSION_NO: &str = "no";
+const ERROR_NOT_FOUND: &str = "org.freedesktop.portal.Error.NotFound";
+
+#[derive(PartialEq)]
+pub enum Permission {
+    Unset,
+    Granted,
+    Denied,
+}
+
+pub struct PermissionStore<'a> {
+    permission_store_proxy: dbus_proxy::xdg_desktop_portal::PermissionStore<'a>,
+}
+
+impl<'a> PermissionStore<'a> {
+    pub async fn new(connection: &zbus::Connection) -> zbus::Result<Self> {
+        let permission_store_proxy =
+            dbus_proxy::xdg_desktop_portal::PermissionStore::new(connection).await?;
+
+        Ok(Self {
+            permission_store_proxy,
+        })
+    }
+
+    pub async fn get(&self, app_id: &str) -> Permission {
+        // Host apps have no app id to store a permission under, so they are always exempt
+        if app_id.is_empty() {
+            return Permission::Granted;
+        }
+        let permissions = match self.lookup().await {
+            Ok(permissions) => permissions,
+            Err(err) => {
+                log::warn!("Could not look up the screencast permissions: {err}");
+                return Permission::Unset;
+            }
+        };
+        match permissions.get(app_id).and_then(|x| x.first()) {
+            Some(x) if x == PERMISSION_YES => Permission::Granted,
+            Some(x) if x == PERMISSION_NO => Permission::Denied,
+            _ => Permission::Unset,
+        }
+    }
+
+    pub async fn grant(&self, app_id: &str) {
+        if app_id.is_empty() {
+            return;
+        }
+        if let Err(err) = self
+            .permission_store_proxy
+            .set_permission(TABLE, true, ID, app_id, &[PERMISSION_YES])
+            .await
+        {
+            log::warn!("Could not store the screencast permission of '{app_id}': {err}");
+        }
+    }
+
+    pub async fn get_granted_apps(&self) -> zbus::Result<HashSet<String>> {
+        Ok(get_granted_apps(&self.lookup().await?))
+    }
+
+    pub async fn receive_granted_apps_changed(
+        &self,
+    ) -> zbus::Result<impl futures_util::Stream<Item = HashSet<String>> + use<'a>> {
+        let stream = self
+            .permission_store_proxy
+            .receive_changed_with_args(&[(0, TABLE), (1, ID)])
+            .await?;
+        Ok(stream.filter_map(|signal| std::future::ready(get_changed_granted_apps(&signal))))
+    }
+
+    async fn lookup(&self) -> zbus::Result<HashMap<String, Vec<String>>> {
+        match self.permission_store_proxy.lookup(TABLE, ID).await {
+            Ok((permissions, _)) => Ok(permissions),
+            Err(zbus::Error::MethodError(name, _, _)) if name == ERROR_NOT_FOUND => {
+                Ok(HashMap::new())
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+fn get_changed_granted_apps(
+    signal: &dbus_proxy::xdg_desktop_portal::PermissionStoreChanged,
+) -> Option<HashSet<String>> {
+    let args = match signal.args() {
+        Ok(args) => args,
+        Err(err) => {
+            log::error!("org.freedesktop.impl.portal.PermissionStore - Changed(): {err}");
+            return None;
+        }
+    };
+    if *args.deleted() {
+        return Some(HashSet::new());
+    }
+    Some(get_granted_apps(args.permissions()))
+}
+
+fn get_granted_apps<S: AsRef<str>>(permissions: &HashMap<S, Vec<S>>) -> HashSet<String> {
+    permissions
+        .iter()
+        .filter(|(_, x)| x.first().is_some_and(|x| x.as_ref() == PERMISSION_YES))
+        .map(|(app_id, _)| app_id.as_ref().to_owned())
+        .collect()
+}
@@ -0,0 +1,79 @@
+use crate::config::{self, PolicyAction};
+
+pub fn evaluate(app_id: &str) -> PolicyAction {
+    let config = config::get();
+    let rule = config.policy.iter().find(|x| glob_match(&x.app_id, app_id));
+    match rule {
+        Some(rule) => {
+            log::debug!("Policy rule '{}' matched '{app_id}'", rule.app_id);
+            rule.action.clone()
+        }
+        None => PolicyAction::Ask,
+    }
+}
+
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<_> = pattern.chars().collect();
+    let text: Vec<_> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut last_star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                last_star = Some((p, t));
+                p += 1;
+            }
+            Some(&x) if x == '?' || x == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match last_star {
+                Some((star, matched)) => {
+                    last_star = Some((star, matched + 1));
+                    p = star + 1;
+                    t = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|x| *x == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn star_matches_any_sequence() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "org.example.App"));
+        assert!(glob_match("org.example.*", "org.example.App"));
+        assert!(glob_match("org.example.*", "org.example."));
+        assert!(!glob_match("org.example.*", "org.other.App"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(glob_match("DP-?", "DP-1"));
+        assert!(!glob_match("DP-?", "DP-"));
+        assert!(!glob_match("DP-?", "DP-12"));
+    }
+
+    #[test]
+    fn multiple_stars() {
+        assert!(glob_match("*Signage*", "Digital Signage Player"));
+        assert!(glob_match("*a*b*c*", "xxaxxbxxcxx"));
+        assert!(glob_match("**", "abc"));
+        assert!(!glob_match("*a*b*c*", "xxaxxcxxbxx"));
+        assert!(!glob_match("*a*a*a*a*a*a*b", &"a".repeat(100)));
+    }
+
+    #[test]
+    fn literal_match() {
+        assert!(glob_match("org.example.App", "org.example.App"));
+        assert!(!glob_match("org.example.App", "org.example.Ap"));
+        assert!(!glob_match("", "a"));
+        assert!(glob_match("", ""));
+    }
+}
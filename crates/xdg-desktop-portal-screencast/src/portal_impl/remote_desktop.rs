@@ -0,0 +1,528 @@
+use super::error::Error;
+use super::screencast::encode_streams;
+use super::{Request, ScreenCast, ScreenCastSession};
+use crate::config::PolicyAction;
+use crate::dbus_proxy;
+use libsourceselector::Devices;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU32, Ordering},
+    },
+};
+use zbus::zvariant;
+
+const DEVICE_TYPE_KEYBOARD: u32 = 1 << 0;
+const DEVICE_TYPE_POINTER: u32 = 1 << 1;
+const DEVICE_TYPE_TOUCHSCREEN: u32 = 1 << 2;
+
+const MUFFIN_AXIS_FLAG_FINISH: u32 = 1 << 0;
+const MUFFIN_AXIS_FLAG_SOURCE_FINGER: u32 = 1 << 2;
+
+#[derive(Clone)]
+pub struct RemoteDesktop {
+    inner: Arc<RemoteDesktopInner<'static>>,
+}
+
+impl RemoteDesktop {
+    pub async fn new(
+        connection: zbus::Connection,
+        screencast_ctx: ScreenCast,
+    ) -> zbus::Result<Self> {
+        let inner = Arc::new(RemoteDesktopInner::new(connection, screencast_ctx).await?);
+
+        Ok(Self { inner })
+    }
+
+    pub async fn on_compositor_appeared(&self) -> zbus::Result<()> {
+        self.inner.on_compositor_appeared().await
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.RemoteDesktop")]
+impl RemoteDesktop {
+    async fn create_session(
+        &self,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+        handle: zvariant::OwnedObjectPath,
+        session_handle: zvariant::OwnedObjectPath,
+        app_id: String,
+        options: HashMap<String, zvariant::OwnedValue>,
+    ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        log::debug!("CreateSession():");
+        log::debug!("\thandle: {handle}");
+        log::debug!("\tsession_handle: {session_handle}");
+        log::debug!("\tapp_id: {app_id}");
+        log::debug!("\toptions: {options:?}");
+
+        let frontend = header.sender().map(|x| x.to_owned());
+        self.inner
+            .create_session(handle, session_handle, app_id, frontend, options)
+            .await
+    }
+
+    async fn select_devices(
+        &self,
+        handle: zvariant::OwnedObjectPath,
+        session_handle: zvariant::OwnedObjectPath,
+        app_id: String,
+        options: HashMap<String, zvariant::OwnedValue>,
+    ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        log::debug!("SelectDevices():");
+        log::debug!("\thandle: {handle}");
+        log::debug!("\tsession_handle: {session_handle}");
+        log::debug!("\tapp_id: {app_id}");
+        log::debug!("\toptions: {options:?}");
+
+        self.inner
+            .select_devices(handle, session_handle, app_id, options)
+            .await
+    }
+
+    async fn start(
+        &self,
+        handle: zvariant::OwnedObjectPath,
+        session_handle: zvariant::OwnedObjectPath,
+        app_id: String,
+        parent_window: String,
+        options: HashMap<String, zvariant::OwnedValue>,
+    ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        log::debug!("Start():");
+        log::debug!("\thandle: {handle}");
+        log::debug!("\tsession_handle: {session_handle}");
+        log::debug!("\tapp_id: {app_id}");
+        log::debug!("\tparent_window: {parent_window}");
+        log::debug!("\toptions: {options:?}");
+
+        self.inner
+            .start(handle, session_handle, app_id, parent_window, options)
+            .await
+    }
+
+    async fn notify_pointer_motion(
+        &self,
+        session_handle: zvariant::OwnedObjectPath,
+        _options: HashMap<String, zvariant::OwnedValue>,
+        dx: f64,
+        dy: f64,
+    ) -> zbus::fdo::Result<()> {
+        let (_, remote_desktop_session_proxy) = self
+            .inner
+            .get_device(&session_handle, DEVICE_TYPE_POINTER)
+            .await?;
+        Ok(remote_desktop_session_proxy
+            .notify_pointer_motion_relative(dx, dy)
+            .await?)
+    }
+
+    async fn notify_pointer_motion_absolute(
+        &self,
+        session_handle: zvariant::OwnedObjectPath,
+        _options: HashMap<String, zvariant::OwnedValue>,
+        stream: u32,
+        x: f64,
+        y: f64,
+    ) -> zbus::fdo::Result<()> {
+        let (session, remote_desktop_session_proxy) = self
+            .inner
+            .get_device(&session_handle, DEVICE_TYPE_POINTER)
+            .await?;
+        let stream_path = get_stream_path(&session, stream).await?;
+        Ok(remote_desktop_session_proxy
+            .notify_pointer_motion_absolute(&stream_path, x, y)
+            .await?)
+    }
+
+    async fn notify_pointer_button(
+        &self,
+        session_handle: zvariant::OwnedObjectPath,
+        _options: HashMap<String, zvariant::OwnedValue>,
+        button: i32,
+        state: u32,
+    ) -> zbus::fdo::Result<()> {
+        let (_, remote_desktop_session_proxy) = self
+            .inner
+            .get_device(&session_handle, DEVICE_TYPE_POINTER)
+            .await?;
+        Ok(remote_desktop_session_proxy
+            .notify_pointer_button(button, state != 0)
+            .await?)
+    }
+
+    async fn notify_pointer_axis(
+        &self,
+        session_handle: zvariant::OwnedObjectPath,
+        options: HashMap<String, zvariant::OwnedValue>,
+        dx: f64,
+        dy: f64,
+    ) -> zbus::fdo::Result<()> {
+        let (_, remote_desktop_session_proxy) = self
+            .inner
+            .get_device(&session_handle, DEVICE_TYPE_POINTER)
+            .await?;
+        let finish = match options.get("finish") {
+            Some(finish) => finish.downcast_ref::<bool>().map_err(zbus::Error::from)?,
+            None => false,
+        };
+        let mut flags = MUFFIN_AXIS_FLAG_SOURCE_FINGER;
+        if finish {
+            flags |= MUFFIN_AXIS_FLAG_FINISH;
+        }
+        Ok(remote_desktop_session_proxy
+            .notify_pointer_axis(dx, dy, flags)
+            .await?)
+    }
+
+    async fn notify_pointer_axis_discrete(
+        &self,
+        session_handle: zvariant::OwnedObjectPath,
+        _options: HashMap<String, zvariant::OwnedValue>,
+        axis: u32,
+        steps: i32,
+    ) -> zbus::fdo::Result<()> {
+        let (_, remote_desktop_session_proxy) = self
+            .inner
+            .get_device(&session_handle, DEVICE_TYPE_POINTER)
+            .await?;
+        Ok(remote_desktop_session_proxy
+            .notify_pointer_axis_discrete(axis, steps)
+            .await?)
+    }
+
+    async fn notify_keyboard_keycode(
+        &self,
+        session_handle: zvariant::OwnedObjectPath,
+        _options: HashMap<String, zvariant::OwnedValue>,
+        keycode: i32,
+        state: u32,
+    ) -> zbus::fdo::Result<()> {
+        let (_, remote_desktop_session_proxy) = self
+            .inner
+            .get_device(&session_handle, DEVICE_TYPE_KEYBOARD)
+            .await?;
+        let keycode = u32::try_from(keycode)
+            .map_err(|_| zbus::fdo::Error::InvalidArgs(format!("invalid keycode: {keycode}")))?;
+        Ok(remote_desktop_session_proxy
+            .notify_keyboard_keycode(keycode, state != 0)
+            .await?)
+    }
+
+    async fn notify_keyboard_keysym(
+        &self,
+        session_handle: zvariant::OwnedObjectPath,
+        _options: HashMap<String, zvariant::OwnedValue>,
+        keysym: i32,
+        state: u32,
+    ) -> zbus::fdo::Result<()> {
+        let (_, remote_desktop_session_proxy) = self
+            .inner
+            .get_device(&session_handle, DEVICE_TYPE_KEYBOARD)
+            .await?;
+        let keysym = u32::try_from(keysym)
+            .map_err(|_| zbus::fdo::Error::InvalidArgs(format!("invalid keysym: {keysym}")))?;
+        Ok(remote_desktop_session_proxy
+            .notify_keyboard_keysym(keysym, state != 0)
+            .await?)
+    }
+
+    async fn notify_touch_down(
+        &self,
+        session_handle: zvariant::OwnedObjectPath,
+        _options: HashMap<String, zvariant::OwnedValue>,
+        stream: u32,
+        slot: u32,
+        x: f64,
+        y: f64,
+    ) -> zbus::fdo::Result<()> {
+        let (session, remote_desktop_session_proxy) = self
+            .inner
+            .get_device(&session_handle, DEVICE_TYPE_TOUCHSCREEN)
+            .await?;
+        let stream_path = get_stream_path(&session, stream).await?;
+        Ok(remote_desktop_session_proxy
+            .notify_touch_down(&stream_path, slot, x, y)
+            .await?)
+    }
+
+    async fn notify_touch_motion(
+        &self,
+        session_handle: zvariant::OwnedObjectPath,
+        _options: HashMap<String, zvariant::OwnedValue>,
+        stream: u32,
+        slot: u32,
+        x: f64,
+        y: f64,
+    ) -> zbus::fdo::Result<()> {
+        let (session, remote_desktop_session_proxy) = self
+            .inner
+            .get_device(&session_handle, DEVICE_TYPE_TOUCHSCREEN)
+            .await?;
+        let stream_path = get_stream_path(&session, stream).await?;
+        Ok(remote_desktop_session_proxy
+            .notify_touch_motion(&stream_path, slot, x, y)
+            .await?)
+    }
+
+    async fn notify_touch_up(
+        &self,
+        session_handle: zvariant::OwnedObjectPath,
+        _options: HashMap<String, zvariant::OwnedValue>,
+        slot: u32,
+    ) -> zbus::fdo::Result<()> {
+        let (_, remote_desktop_session_proxy) = self
+            .inner
+            .get_device(&session_handle, DEVICE_TYPE_TOUCHSCREEN)
+            .await?;
+        Ok(remote_desktop_session_proxy.notify_touch_up(slot).await?)
+    }
+
+    #[zbus(property)]
+    fn available_device_types(&self) -> u32 {
+        self.inner.available_device_types.load(Ordering::Relaxed)
+    }
+
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        RemoteDesktopInner::version()
+    }
+}
+
+struct RemoteDesktopInner<'a> {
+    connection: zbus::Connection,
+    screencast_ctx: ScreenCast,
+    remote_desktop_proxy: RwLock<dbus_proxy::muffin::RemoteDesktop<'a>>,
+    available_device_types: AtomicU32,
+}
+
+impl RemoteDesktopInner<'static> {
+    async fn new(connection: zbus::Connection, screencast_ctx: ScreenCast) -> zbus::Result<Self> {
+        let remote_desktop_proxy = dbus_proxy::muffin::RemoteDesktop::new(&connection).await?;
+        let available_device_types = get_available_device_types(&remote_desktop_proxy).await;
+
+        Ok(Self {
+            connection,
+            screencast_ctx,
+            remote_desktop_proxy: RwLock::new(remote_desktop_proxy),
+            available_device_types: AtomicU32::new(available_device_types),
+        })
+    }
+
+    async fn on_compositor_appeared(&self) -> zbus::Result<()> {
+        let remote_desktop_proxy = dbus_proxy::muffin::RemoteDesktop::new(&self.connection).await?;
+        let available_device_types = get_available_device_types(&remote_desktop_proxy).await;
+        *self.remote_desktop_proxy.write().unwrap() = remote_desktop_proxy;
+        self.available_device_types
+            .store(available_device_types, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn get_remote_desktop_session(
+        &self,
+        session_handle: &zvariant::OwnedObjectPath,
+    ) -> Result<
+        (
+            Arc<ScreenCastSession<'static>>,
+            dbus_proxy::muffin::RemoteDesktopSession<'static>,
+        ),
+        Error,
+    > {
+        let session = self.screencast_ctx.get_session(session_handle)?;
+        let remote_desktop_session_proxy = session
+            .get_remote_desktop_session()
+            .cloned()
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "'{session_handle}' is not a remote desktop session"
+                ))
+            })?;
+        Ok((session, remote_desktop_session_proxy))
+    }
+
+    async fn get_device(
+        &self,
+        session_handle: &zvariant::OwnedObjectPath,
+        device_type: u32,
+    ) -> Result<
+        (
+            Arc<ScreenCastSession<'static>>,
+            dbus_proxy::muffin::RemoteDesktopSession<'static>,
+        ),
+        Error,
+    > {
+        let (session, remote_desktop_session_proxy) =
+            self.get_remote_desktop_session(session_handle)?;
+        if session.get_devices().await & device_type == 0 {
+            return Err(Error::InvalidArgument(format!(
+                "device type {device_type:#x} was not selected for '{session_handle}'"
+            )));
+        }
+        Ok((session, remote_desktop_session_proxy))
+    }
+
+    async fn create_session(
+        &self,
+        handle: zvariant::OwnedObjectPath,
+        session_handle: zvariant::OwnedObjectPath,
+        app_id: String,
+        frontend: Option<zbus::names::UniqueName<'static>>,
+        _options: HashMap<String, zvariant::OwnedValue>,
+    ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        let connection = self.connection.clone();
+        let body = async {
+            let remote_desktop_proxy = self.remote_desktop_proxy.read().unwrap().clone();
+            let remote_desktop_session_proxy = remote_desktop_proxy.create_session().await?;
+            self.screencast_ctx
+                .add_session(
+                    session_handle,
+                    app_id,
+                    frontend,
+                    Some(remote_desktop_session_proxy),
+                )
+                .await
+        };
+        match Request::run(&connection, &handle, body).await {
+            Ok(_) => {
+                _ = Request::emit_response(&connection, &handle, 0, HashMap::new()).await;
+                (0, HashMap::new())
+            }
+            Err(Error::Closed) => (1, HashMap::new()),
+            Err(err) => {
+                log::error!("create_session(): {err}");
+                let response = err.response();
+                _ = Request::emit_response(&connection, &handle, response, HashMap::new()).await;
+                (response, HashMap::new())
+            }
+        }
+    }
+
+    async fn select_devices(
+        &self,
+        handle: zvariant::OwnedObjectPath,
+        session_handle: zvariant::OwnedObjectPath,
+        _app_id: String,
+        options: HashMap<String, zvariant::OwnedValue>,
+    ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        let connection = self.connection.clone();
+        let body = async {
+            let available_device_types = self.available_device_types.load(Ordering::Relaxed);
+            let device_types = match options.get("types") {
+                Some(types) => types.downcast_ref::<u32>()?,
+                None => available_device_types,
+            };
+            if device_types == 0 || device_types & !available_device_types != 0 {
+                return Err(Error::InvalidArgument(format!(
+                    "unsupported device types: {device_types:#x}"
+                )));
+            }
+            let (session, _) = self.get_remote_desktop_session(&session_handle)?;
+            session.select_devices(device_types).await;
+            Ok::<_, Error>(())
+        };
+        match Request::run(&connection, &handle, body).await {
+            Ok(_) => {
+                _ = Request::emit_response(&connection, &handle, 0, HashMap::new()).await;
+                (0, HashMap::new())
+            }
+            Err(Error::Closed) => (1, HashMap::new()),
+            Err(err) => {
+                log::error!("select_devices(): {err}");
+                let response = err.response();
+                _ = Request::emit_response(&connection, &handle, response, HashMap::new()).await;
+                (response, HashMap::new())
+            }
+        }
+    }
+
+    async fn start(
+        &self,
+        handle: zvariant::OwnedObjectPath,
+        session_handle: zvariant::OwnedObjectPath,
+        _app_id: String,
+        _parent_window: String,
+        _options: HashMap<String, zvariant::OwnedValue>,
+    ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        let connection = self.connection.clone();
+        let body = async {
+            let (session, _) = self.get_remote_desktop_session(&session_handle)?;
+            let (policy, _) = self
+                .screencast_ctx
+                .check_permission(session.get_app_id())
+                .await?;
+            let device_types = session.get_devices().await;
+            if device_types != 0 && !matches!(policy, PolicyAction::Select { .. }) {
+                let devices = session.confirm_devices(to_devices(device_types)).await?;
+                session.select_devices(from_devices(devices)).await;
+            }
+            let pipewire_streams = session.start().await?;
+            Ok::<_, Error>(HashMap::from([
+                ("devices".into(), session.get_devices().await.into()),
+                ("streams".into(), encode_streams(pipewire_streams)?),
+            ]))
+        };
+        match Request::run(&connection, &handle, body).await {
+            Ok(results) => {
+                _ = Request::emit_response(&connection, &handle, 0, HashMap::new()).await;
+                (0, results)
+            }
+            Err(Error::Closed) => (1, HashMap::new()),
+            Err(err) => {
+                log::error!("start(): {err}");
+                let response = err.response();
+                _ = Request::emit_response(&connection, &handle, response, HashMap::new()).await;
+                (response, HashMap::new())
+            }
+        }
+    }
+
+    fn version() -> u32 {
+        1
+    }
+}
+
+async fn get_available_device_types(
+    remote_desktop_proxy: &dbus_proxy::muffin::RemoteDesktop<'_>,
+) -> u32 {
+    let all_device_types = DEVICE_TYPE_KEYBOARD | DEVICE_TYPE_POINTER | DEVICE_TYPE_TOUCHSCREEN;
+    match remote_desktop_proxy.supported_device_types().await {
+        Ok(device_types) => device_types & all_device_types,
+        Err(err) => {
+            log::warn!("Could not query the Muffin RemoteDesktop device types: {err}");
+            all_device_types
+        }
+    }
+}
+
+fn to_devices(device_types: u32) -> Devices {
+    Devices {
+        keyboard: device_types & DEVICE_TYPE_KEYBOARD != 0,
+        pointer: device_types & DEVICE_TYPE_POINTER != 0,
+        touchscreen: device_types & DEVICE_TYPE_TOUCHSCREEN != 0,
+    }
+}
+
+fn from_devices(devices: Devices) -> u32 {
+    let mut device_types = 0;
+    if devices.keyboard {
+        device_types |= DEVICE_TYPE_KEYBOARD;
+    }
+    if devices.pointer {
+        device_types |= DEVICE_TYPE_POINTER;
+    }
+    if devices.touchscreen {
+        device_types |= DEVICE_TYPE_TOUCHSCREEN;
+    }
+    device_types
+}
+
+async fn get_stream_path(
+    session: &ScreenCastSession<'_>,
+    pipewire_stream_id: u32,
+) -> zbus::fdo::Result<zvariant::OwnedObjectPath> {
+    session
+        .get_stream_path(pipewire_stream_id)
+        .await
+        .ok_or_else(|| {
+            zbus::fdo::Error::InvalidArgs(format!("unknown stream: {pipewire_stream_id}"))
+        })
+}
@@ -0,0 +1,56 @@
+use super::error::Error;
+use std::collections::HashMap;
+use zbus::zvariant;
+
+pub struct Request {
+    handle: zvariant::OwnedObjectPath,
+    cancel: std::sync::Arc<tokio::sync::Notify>,
+}
+
+impl Request {
+    pub async fn run<T>(
+        connection: &zbus::Connection,
+        handle: &zvariant::OwnedObjectPath,
+        body: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        let cancel = std::sync::Arc::new(tokio::sync::Notify::new());
+        let request = Self {
+            handle: handle.clone(),
+            cancel: cancel.clone(),
+        };
+        connection.object_server().at(handle, request).await?;
+        let result = tokio::select! {
+            result = body => result,
+            _ = cancel.notified() => Err(Error::Closed),
+        };
+        _ = connection.object_server().remove::<Self, _>(handle).await;
+        result
+    }
+
+    pub async fn emit_response(
+        connection: &zbus::Connection,
+        handle: &zvariant::ObjectPath<'_>,
+        response: u32,
+        results: HashMap<String, zvariant::OwnedValue>,
+    ) -> zbus::Result<()> {
+        connection
+            .emit_signal(
+                Some("org.freedesktop.portal.Desktop"),
+                handle,
+                "org.freedesktop.portal.Request",
+                "Response",
+                &(response, results),
+            )
+            .await
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.Request")]
+impl Request {
+    async fn close(&self) {
+        log::debug!("Close():");
+        log::debug!("\thandle: {}", self.handle);
+
+        self.cancel.notify_one();
+    }
+}
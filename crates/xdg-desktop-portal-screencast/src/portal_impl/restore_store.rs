@@ -0,0 +1,115 @@
+use crate::config;
+use libsourceselector::{SerdeJson, Source};
+use std::collections::HashMap;
+
+pub const PERSIST_MODE_NONE: u32 = 0;
+pub const PERSIST_MODE_TRANSIENT: u32 = 1;
+pub const PERSIST_MODE_PERSISTENT: u32 = 2;
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct RestoreData {
+    pub sources: Vec<Source>,
+}
+
+type RestoreDataMap = HashMap<String, HashMap<String, RestoreData>>;
+
+pub struct RestoreStore {
+    transient: RestoreDataMap,
+    persistent: RestoreDataMap,
+}
+
+impl RestoreStore {
+    pub fn load() -> Self {
+        let persistent = match Self::load_persistent() {
+            Ok(persistent) => persistent,
+            Err(err) => {
+                log::warn!("Could not load the persistent restore data: {err}");
+                RestoreDataMap::new()
+            }
+        };
+
+        Self {
+            transient: RestoreDataMap::new(),
+            persistent,
+        }
+    }
+
+    pub fn take(&mut self, app_id: &str, token: &str) -> Option<RestoreData> {
+        if let Some(restore_data) = take_from(&mut self.transient, app_id, token) {
+            return Some(restore_data);
+        }
+        let restore_data = take_from(&mut self.persistent, app_id, token)?;
+        self.save_persistent();
+        Some(restore_data)
+    }
+
+    pub fn insert(&mut self, app_id: &str, persist_mode: u32, restore_data: RestoreData) -> String {
+        let token = uuid::Uuid::new_v4().to_string();
+        if persist_mode == PERSIST_MODE_PERSISTENT {
+            self.persistent
+                .entry(app_id.to_owned())
+                .or_default()
+                .insert(token.clone(), restore_data);
+            self.save_persistent();
+        } else {
+            self.transient
+                .entry(app_id.to_owned())
+                .or_default()
+                .insert(token.clone(), restore_data);
+        }
+        token
+    }
+
+    pub fn forget_transient(&mut self, app_id: &str) {
+        self.transient.remove(app_id);
+    }
+
+    pub fn forget(&mut self, app_id: &str) {
+        self.transient.remove(app_id);
+        if self.persistent.remove(app_id).is_some() {
+            self.save_persistent();
+        }
+    }
+
+    fn load_persistent() -> Result<RestoreDataMap, Box<dyn std::error::Error>> {
+        let path = get_restore_data_path()?;
+        let json = match std::fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(RestoreDataMap::new());
+            }
+            Err(err) => return Err(format!("Could not read '{path}': {err}").into()),
+        };
+        Ok(RestoreDataMap::from_json(&json)?)
+    }
+
+    fn save_persistent(&self) {
+        let body = || -> Result<(), Box<dyn std::error::Error>> {
+            let path = get_restore_data_path()?;
+            if let Some(dir) = std::path::Path::new(&path).parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&path, self.persistent.to_json()?)?;
+            Ok(())
+        };
+        if let Err(err) = body() {
+            log::error!("Could not save the persistent restore data: {err}");
+        }
+    }
+}
+
+fn take_from(map: &mut RestoreDataMap, app_id: &str, token: &str) -> Option<RestoreData> {
+    let tokens = map.get_mut(app_id)?;
+    let restore_data = tokens.remove(token)?;
+    if tokens.is_empty() {
+        map.remove(app_id);
+    }
+    Some(restore_data)
+}
+
+fn get_restore_data_path() -> Result<String, Box<dyn std::error::Error>> {
+    Ok(format!(
+        "{}/xdg-desktop-portal-screencast/restore-data.json",
+        config::get_xdg_dir("XDG_STATE_HOME", ".local/state")?
+    ))
+}
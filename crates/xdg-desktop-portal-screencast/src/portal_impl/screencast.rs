@@ -1,32 +1,98 @@
-use super::ScreenCastSession as Session;
+use super::error::Error;
+use super::permission_store::{Permission, PermissionStore};
+use super::policy;
+use super::restore_store::{PERSIST_MODE_NONE, PERSIST_MODE_PERSISTENT, RestoreStore};
+use super::screencast_session::SelectSourcesOptions;
+use super::source_selector;
+use super::{Request, ScreenCastSession, Session};
+use crate::config::{self, PolicyAction};
 use crate::dbus_proxy;
-use std::{collections::HashMap, str::FromStr};
+use futures_util::StreamExt;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicU32, Ordering},
+    },
+};
 use zbus::zvariant;
 
-const SOURCE_TYPE_MONITOR: u32 = 1 << 0;
-const SOURCE_TYPE_WINDOW: u32 = 1 << 1;
-const _SOURCE_TYPE_VIRTUAL: u32 = 1 << 2;
+pub(super) const SOURCE_TYPE_MONITOR: u32 = 1 << 0;
+pub(super) const SOURCE_TYPE_WINDOW: u32 = 1 << 1;
+pub(super) const SOURCE_TYPE_VIRTUAL: u32 = 1 << 2;
 
-const CURSOR_TYPE_HIDDEN: u32 = 1 << 0;
-const _CURSOR_TYPE_EMBEDDED: u32 = 1 << 1;
-const CURSOR_TYPE_METADATA: u32 = 1 << 2;
+pub(super) const CURSOR_TYPE_HIDDEN: u32 = 1 << 0;
+pub(super) const CURSOR_TYPE_EMBEDDED: u32 = 1 << 1;
+pub(super) const CURSOR_TYPE_METADATA: u32 = 1 << 2;
+
+const MUFFIN_RECORD_VIRTUAL_VERSION: i32 = 4;
+
+const RESTORE_DATA_VENDOR: &str = "xdg-desktop-portal-screencast";
+const RESTORE_DATA_VERSION: u32 = 1;
 
 #[derive(Clone)]
 pub struct ScreenCast {
-    inner: std::sync::Arc<tokio::sync::Mutex<ScreenCastInner<'static>>>,
+    inner: Arc<ScreenCastInner<'static>>,
 }
 
 impl ScreenCast {
     pub async fn new(connection: zbus::Connection) -> zbus::Result<Self> {
-        let inner = std::sync::Arc::new(tokio::sync::Mutex::new(
-            ScreenCastInner::new(connection).await?,
-        ));
+        let inner = Arc::new(ScreenCastInner::new(connection).await?);
 
         Ok(Self { inner })
     }
 
     pub async fn on_app_closed(&self, app_id: &str) {
-        self.inner.lock().await.on_app_closed(app_id).await
+        self.inner.on_app_closed(app_id).await
+    }
+
+    pub async fn close_session(&self, session_handle: &zvariant::OwnedObjectPath) {
+        self.inner.close_session(session_handle).await
+    }
+
+    pub async fn on_permission_revoked(&self, app_id: &str) {
+        self.inner.on_permission_revoked(app_id).await
+    }
+
+    pub async fn close_all_sessions(&self) {
+        self.inner.close_all_sessions().await
+    }
+
+    pub async fn on_compositor_appeared(&self) -> zbus::Result<()> {
+        self.inner.on_compositor_appeared().await
+    }
+
+    pub async fn add_session(
+        &self,
+        session_handle: zvariant::OwnedObjectPath,
+        app_id: String,
+        frontend: Option<zbus::names::UniqueName<'static>>,
+        remote_desktop_session_proxy: Option<dbus_proxy::muffin::RemoteDesktopSession<'static>>,
+    ) -> Result<(), Error> {
+        self.inner
+            .add_session(
+                self.clone(),
+                session_handle,
+                app_id,
+                frontend,
+                remote_desktop_session_proxy,
+            )
+            .await
+    }
+
+    pub fn get_session(
+        &self,
+        session_handle: &zvariant::OwnedObjectPath,
+    ) -> Result<Arc<ScreenCastSession<'static>>, Error> {
+        self.inner.get_session(session_handle)
+    }
+
+    pub async fn check_permission(
+        &self,
+        app_id: &str,
+    ) -> Result<(PolicyAction, Permission), Error> {
+        self.inner.check_permission(app_id).await
     }
 }
 
@@ -34,6 +100,7 @@ impl ScreenCast {
 impl ScreenCast {
     async fn create_session(
         &self,
+        #[zbus(header)] header: zbus::message::Header<'_>,
         handle: zvariant::OwnedObjectPath,
         session_handle: zvariant::OwnedObjectPath,
         app_id: String,
@@ -45,10 +112,16 @@ impl ScreenCast {
         log::debug!("\tapp_id: {app_id}");
         log::debug!("\toptions: {options:?}");
 
+        let frontend = header.sender().map(|x| x.to_owned());
         self.inner
-            .lock()
-            .await
-            .create_session(handle, session_handle, app_id, options)
+            .create_session(
+                self.clone(),
+                handle,
+                session_handle,
+                app_id,
+                frontend,
+                options,
+            )
             .await
     }
 
@@ -66,8 +139,6 @@ impl ScreenCast {
         log::debug!("\toptions: {options:?}");
 
         self.inner
-            .lock()
-            .await
             .select_sources(handle, session_handle, app_id, options)
             .await
     }
@@ -88,8 +159,6 @@ impl ScreenCast {
         log::debug!("\toptions: {options:?}");
 
         self.inner
-            .lock()
-            .await
             .start(handle, session_handle, app_id, parent_window, options)
             .await
     }
@@ -101,7 +170,7 @@ impl ScreenCast {
 
     #[zbus(property)]
     fn available_source_types(&self) -> u32 {
-        ScreenCastInner::available_source_types()
+        self.inner.available_source_types.load(Ordering::Relaxed)
     }
 
     #[zbus(property, name = "version")]
@@ -112,89 +181,348 @@ impl ScreenCast {
 
 struct ScreenCastInner<'a> {
     connection: zbus::Connection,
-    screencast_proxy: dbus_proxy::muffin::ScreenCast<'a>,
-    screencast_sessions: HashMap<zvariant::OwnedObjectPath, Session<'a>>,
+    screencast_proxy: RwLock<dbus_proxy::muffin::ScreenCast<'a>>,
+    available_source_types: AtomicU32,
+    screencast_sessions: Mutex<HashMap<zvariant::OwnedObjectPath, Arc<ScreenCastSession<'a>>>>,
+    restore_store: Mutex<RestoreStore>,
+    permission_store: PermissionStore<'a>,
 }
 
 impl<'a> ScreenCastInner<'a> {
     async fn new(connection: zbus::Connection) -> zbus::Result<Self> {
         let screencast_proxy = dbus_proxy::muffin::ScreenCast::new(&connection).await?;
+        let available_source_types = get_available_source_types(&screencast_proxy).await;
+        let permission_store = PermissionStore::new(&connection).await?;
 
         Ok(Self {
             connection,
-            screencast_proxy,
-            screencast_sessions: HashMap::new(),
+            screencast_proxy: RwLock::new(screencast_proxy),
+            available_source_types: AtomicU32::new(available_source_types),
+            screencast_sessions: Mutex::new(HashMap::new()),
+            restore_store: Mutex::new(RestoreStore::load()),
+            permission_store,
         })
     }
 
-    async fn on_app_closed(&mut self, app_id: &str) {
-        let iter = self
+    async fn on_app_closed(&self, app_id: &str) {
+        let closed_sessions: Vec<_> = self
             .screencast_sessions
-            .extract_if(|_, x| x.get_app_id() == app_id);
-        for (_, session) in iter {
+            .lock()
+            .unwrap()
+            .extract_if(|_, x| x.get_app_id() == app_id)
+            .collect();
+        for (session_handle, session) in closed_sessions {
             session.close().await;
+            self.unexport_session(&session_handle, true).await;
+        }
+        self.restore_store.lock().unwrap().forget_transient(app_id);
+    }
+
+    async fn on_permission_revoked(&self, app_id: &str) {
+        let closed_sessions: Vec<_> = self
+            .screencast_sessions
+            .lock()
+            .unwrap()
+            .extract_if(|_, x| x.get_app_id() == app_id)
+            .collect();
+        for (session_handle, session) in closed_sessions {
+            session.close().await;
+            self.unexport_session(&session_handle, true).await;
+        }
+        self.restore_store.lock().unwrap().forget(app_id);
+    }
+
+    async fn close_all_sessions(&self) {
+        let closed_sessions: Vec<_> = self.screencast_sessions.lock().unwrap().drain().collect();
+        for (session_handle, session) in closed_sessions {
+            session.close().await;
+            self.unexport_session(&session_handle, true).await;
+        }
+    }
+
+    async fn on_compositor_appeared(&self) -> zbus::Result<()> {
+        let screencast_proxy = dbus_proxy::muffin::ScreenCast::new(&self.connection).await?;
+        let available_source_types = get_available_source_types(&screencast_proxy).await;
+        *self.screencast_proxy.write().unwrap() = screencast_proxy;
+        self.available_source_types
+            .store(available_source_types, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn close_session(&self, session_handle: &zvariant::OwnedObjectPath) {
+        let session = self
+            .screencast_sessions
+            .lock()
+            .unwrap()
+            .remove(session_handle);
+        if let Some(session) = session {
+            session.close().await;
+        }
+        self.unexport_session(session_handle, false).await;
+    }
+
+    async fn on_session_closed(&self, session_handle: &zvariant::OwnedObjectPath) {
+        let session = self
+            .screencast_sessions
+            .lock()
+            .unwrap()
+            .remove(session_handle);
+        if let Some(session) = session {
+            session.close().await;
+            self.unexport_session(session_handle, true).await;
+        }
+    }
+
+    fn get_session(
+        &self,
+        session_handle: &zvariant::OwnedObjectPath,
+    ) -> Result<Arc<ScreenCastSession<'a>>, Error> {
+        self.screencast_sessions
+            .lock()
+            .unwrap()
+            .get(session_handle)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("session for '{session_handle}' not found")))
+    }
+
+    async fn unexport_session(&self, session_handle: &zvariant::ObjectPath<'_>, notify: bool) {
+        if notify {
+            let emit_closed = async {
+                let emitter =
+                    zbus::object_server::SignalEmitter::new(&self.connection, session_handle)?;
+                Session::closed(&emitter).await
+            };
+            if let Err(err) = emit_closed.await {
+                log::error!("Could not emit Closed() on '{session_handle}': {err}");
+            }
         }
+        _ = self
+            .connection
+            .object_server()
+            .remove::<Session, _>(session_handle)
+            .await;
+    }
+
+    async fn add_session(
+        &self,
+        screencast_ctx: ScreenCast,
+        session_handle: zvariant::OwnedObjectPath,
+        app_id: String,
+        frontend: Option<zbus::names::UniqueName<'static>>,
+        remote_desktop_session_proxy: Option<dbus_proxy::muffin::RemoteDesktopSession<'a>>,
+    ) -> Result<(), Error> {
+        if let PolicyAction::Deny = policy::evaluate(&app_id) {
+            return Err(Error::Denied(format!("'{app_id}' is denied by policy")));
+        }
+        let dbus_proxy = zbus::fdo::DBusProxy::new(&self.connection).await?;
+        let mut peers = Vec::new();
+        peers.extend(frontend);
+        if let Some(app) = get_peer_name(&session_handle) {
+            peers.push(app);
+        }
+        let mut peer_streams = Vec::new();
+        for peer in &peers {
+            peer_streams.push(
+                dbus_proxy
+                    .receive_name_owner_changed_with_args(&[(0, peer.as_str())])
+                    .await?,
+            );
+            if !dbus_proxy.name_has_owner(peer.as_ref().into()).await? {
+                return Err(Error::Failed(format!("'{peer}' has disconnected")));
+            }
+        }
+        let mut peer_disconnected =
+            futures_util::stream::select_all(peer_streams).filter(|signal| {
+                let disconnected = signal.args().is_ok_and(|x| x.new_owner().is_none());
+                std::future::ready(disconnected)
+            });
+        let remote_desktop_session_id = match &remote_desktop_session_proxy {
+            Some(remote_desktop_session_proxy) => {
+                Some(remote_desktop_session_proxy.session_id().await?.into())
+            }
+            None => None,
+        };
+        let mut properties = HashMap::new();
+        if let Some(remote_desktop_session_id) = &remote_desktop_session_id {
+            properties.insert("remote-desktop-session-id", remote_desktop_session_id);
+        }
+        let screencast_proxy = self.screencast_proxy.read().unwrap().clone();
+        let screencast_session_proxy = screencast_proxy.create_session(properties).await?;
+        let mut screencast_session_closed = screencast_session_proxy.receive_closed().await?;
+        let mut remote_desktop_session_closed = match &remote_desktop_session_proxy {
+            Some(remote_desktop_session_proxy) => {
+                Some(remote_desktop_session_proxy.receive_closed().await?)
+            }
+            None => None,
+        };
+        let session = ScreenCastSession::new(
+            self.connection.clone(),
+            app_id,
+            screencast_session_proxy,
+            remote_desktop_session_proxy,
+        )
+        .await?;
+        self.connection
+            .object_server()
+            .at(
+                &session_handle,
+                Session::new(screencast_ctx.clone(), session_handle.clone()),
+            )
+            .await?;
+        let mut closed = session.subscribe_closed();
+        self.screencast_sessions
+            .lock()
+            .unwrap()
+            .insert(session_handle.clone(), Arc::new(session));
+
+        tokio::spawn(async move {
+            let remote_desktop_session_closed = async {
+                match &mut remote_desktop_session_closed {
+                    Some(stream) => stream.next().await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                Some(_) = screencast_session_closed.next() => {
+                    log::info!("Muffin closed the session for '{session_handle}'");
+                }
+                Some(_) = remote_desktop_session_closed => {
+                    log::info!("Muffin closed the session for '{session_handle}'");
+                }
+                Some(_) = peer_disconnected.next() => {
+                    log::info!("The client of '{session_handle}' has disconnected");
+                }
+                _ = closed.wait_for(|x| *x) => return,
+            }
+            screencast_ctx
+                .inner
+                .on_session_closed(&session_handle)
+                .await;
+        });
+
+        Ok(())
     }
 
     async fn create_session(
-        &mut self,
+        &self,
+        screencast_ctx: ScreenCast,
         handle: zvariant::OwnedObjectPath,
         session_handle: zvariant::OwnedObjectPath,
         app_id: String,
+        frontend: Option<zbus::names::UniqueName<'static>>,
         _options: HashMap<String, zvariant::OwnedValue>,
     ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
-        let body = async {
-            let connection = self.connection.clone();
-            let screencast_session_proxy =
-                self.screencast_proxy.create_session(HashMap::new()).await?;
-            let session = Session::new(
-                connection,
-                app_id,
-                &session_handle,
-                screencast_session_proxy,
-            )
-            .await?;
-            self.screencast_sessions.insert(session_handle, session);
-            zbus::Result::Ok(())
-        };
-        match body.await {
+        let connection = self.connection.clone();
+        let body = self.add_session(screencast_ctx, session_handle, app_id, frontend, None);
+        match Request::run(&connection, &handle, body).await {
             Ok(_) => {
-                _ = self.emit_request_ack(&handle, 0, HashMap::new()).await;
+                _ = Request::emit_response(&connection, &handle, 0, HashMap::new()).await;
                 (0, HashMap::new())
             }
+            Err(Error::Closed) => (1, HashMap::new()),
             Err(err) => {
                 log::error!("create_session(): {err}");
-                _ = self.emit_request_ack(&handle, 2, HashMap::new()).await;
-                (1, HashMap::new())
+                let response = err.response();
+                _ = Request::emit_response(&connection, &handle, response, HashMap::new()).await;
+                (response, HashMap::new())
             }
         }
     }
 
+    async fn check_permission(&self, app_id: &str) -> Result<(PolicyAction, Permission), Error> {
+        let policy = policy::evaluate(app_id);
+        if let PolicyAction::Deny = policy {
+            return Err(Error::Denied(format!("'{app_id}' is denied by policy")));
+        }
+        let permission = self.permission_store.get(app_id).await;
+        if permission == Permission::Denied {
+            return Err(Error::Denied(format!(
+                "'{app_id}' is not allowed to capture the screen"
+            )));
+        }
+        Ok((policy, permission))
+    }
+
     async fn select_sources(
-        &mut self,
+        &self,
         handle: zvariant::OwnedObjectPath,
         session_handle: zvariant::OwnedObjectPath,
         _app_id: String,
-        _options: HashMap<String, zvariant::OwnedValue>,
+        options: HashMap<String, zvariant::OwnedValue>,
     ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        let connection = self.connection.clone();
         let body = async {
-            self.screencast_sessions
-                .get_mut(&session_handle)
-                .ok_or_else(|| {
-                    zbus::Error::Failure(format!("session for '{session_handle}' not found"))
-                })?
-                .select_sources()
-                .await
+            let source_types = match options.get("types") {
+                Some(types) => types.downcast_ref::<u32>()?,
+                None => SOURCE_TYPE_MONITOR,
+            };
+            let available_source_types = self.available_source_types.load(Ordering::Relaxed);
+            if source_types == 0 || source_types & !available_source_types != 0 {
+                return Err(Error::InvalidArgument(format!(
+                    "unsupported source types: {source_types:#x}"
+                )));
+            }
+            let multiple = match options.get("multiple") {
+                Some(multiple) => multiple.downcast_ref::<bool>()?,
+                None => false,
+            };
+            let cursor_mode = match options.get("cursor_mode") {
+                Some(cursor_mode) => cursor_mode.downcast_ref::<u32>()?,
+                None => {
+                    source_selector::from_cursor_mode(config::get().screencast.default_cursor_mode)
+                }
+            };
+            if !cursor_mode.is_power_of_two() || cursor_mode & !Self::available_cursor_modes() != 0
+            {
+                return Err(Error::InvalidArgument(format!(
+                    "unsupported cursor mode: {cursor_mode:#x}"
+                )));
+            }
+            let persist_mode = match options.get("persist_mode") {
+                Some(persist_mode) => persist_mode.downcast_ref::<u32>()?,
+                None => PERSIST_MODE_NONE,
+            };
+            if persist_mode > PERSIST_MODE_PERSISTENT {
+                return Err(Error::InvalidArgument(format!(
+                    "unsupported persist mode: {persist_mode}"
+                )));
+            }
+            let session = self.get_session(&session_handle)?;
+            let (policy, permission) = self.check_permission(session.get_app_id()).await?;
+            let restore_data = match parse_restore_token(&options)? {
+                Some(token) => self
+                    .restore_store
+                    .lock()
+                    .unwrap()
+                    .take(session.get_app_id(), &token)
+                    .filter(|_| permission == Permission::Granted),
+                None => None,
+            };
+            let options = SelectSourcesOptions {
+                source_types,
+                multiple,
+                cursor_mode,
+                persist_mode,
+                restore_data,
+                policy,
+            };
+            let is_interactive = session.select_sources(&options).await?;
+            if is_interactive {
+                self.permission_store.grant(session.get_app_id()).await;
+            }
+            Ok::<_, Error>(())
         };
-        match body.await {
+        match Request::run(&connection, &handle, body).await {
             Ok(_) => {
-                _ = self.emit_request_ack(&handle, 0, HashMap::new()).await;
+                _ = Request::emit_response(&connection, &handle, 0, HashMap::new()).await;
                 (0, HashMap::new())
             }
+            Err(Error::Closed) => (1, HashMap::new()),
             Err(err) => {
                 log::error!("select_sources(): {err}");
-                _ = self.emit_request_ack(&handle, 2, HashMap::new()).await;
-                (1, HashMap::new())
+                let response = err.response();
+                _ = Request::emit_response(&connection, &handle, response, HashMap::new()).await;
+                (response, HashMap::new())
             }
         }
     }
@@ -207,63 +535,114 @@ impl<'a> ScreenCastInner<'a> {
         _parent_window: String,
         _options: HashMap<String, zvariant::OwnedValue>,
     ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        let connection = self.connection.clone();
         let body = async {
-            let session = self
-                .screencast_sessions
-                .get(&session_handle)
-                .ok_or_else(|| {
-                    zbus::Error::Failure(format!("session for '{session_handle}' not found"))
-                })?;
-            let pipewire_stream_id = session.start().await?;
-
-            let mut streams = zvariant::Array::new(&zvariant::Signature::from_str("(ua{sv})")?);
-            let stream_info = zvariant::StructureBuilder::new()
-                .add_field(pipewire_stream_id)
-                .add_field(HashMap::<&str, zvariant::Value>::new())
-                .build()?;
-            streams.append(stream_info.into())?;
-            log::debug!("ScreenCast started on PipeWire stream ID: {pipewire_stream_id}");
-            zbus::Result::Ok([("streams".into(), zvariant::OwnedValue::try_from(streams)?)].into())
+            let session = self.get_session(&session_handle)?;
+            let pipewire_streams = session.start().await?;
+            let mut results =
+                HashMap::from([("streams".into(), encode_streams(pipewire_streams)?)]);
+            if let Some((persist_mode, restore_data)) = session.get_restore_data().await {
+                let token = self.restore_store.lock().unwrap().insert(
+                    session.get_app_id(),
+                    persist_mode,
+                    restore_data,
+                );
+                results.insert("persist_mode".into(), persist_mode.into());
+                results.insert("restore_data".into(), encode_restore_token(token)?);
+            }
+            Ok::<_, Error>(results)
         };
-        match body.await {
-            Ok(streams) => {
-                _ = self.emit_request_ack(&handle, 0, HashMap::new()).await;
-                (0, streams)
+        match Request::run(&connection, &handle, body).await {
+            Ok(results) => {
+                _ = Request::emit_response(&connection, &handle, 0, HashMap::new()).await;
+                (0, results)
             }
+            Err(Error::Closed) => (1, HashMap::new()),
             Err(err) => {
                 log::error!("start(): {err}");
-                _ = self.emit_request_ack(&handle, 2, HashMap::new()).await;
-                (1, HashMap::new())
+                let response = err.response();
+                _ = Request::emit_response(&connection, &handle, response, HashMap::new()).await;
+                (response, HashMap::new())
             }
         }
     }
 
-    async fn emit_request_ack(
-        &self,
-        request_object: &zvariant::ObjectPath<'_>,
-        response: u32,
-        results: HashMap<String, zvariant::OwnedValue>,
-    ) -> zbus::Result<()> {
-        self.connection
-            .emit_signal(
-                Some("org.freedesktop.portal.Desktop"),
-                request_object,
-                "org.freedesktop.portal.Request",
-                "Response",
-                &(response, results),
-            )
-            .await
-    }
-
     fn available_cursor_modes() -> u32 {
         CURSOR_TYPE_HIDDEN | CURSOR_TYPE_METADATA
     }
 
-    fn available_source_types() -> u32 {
-        SOURCE_TYPE_MONITOR | SOURCE_TYPE_WINDOW
-    }
-
     fn version() -> u32 {
         5
     }
 }
+
+fn get_peer_name(
+    session_handle: &zvariant::ObjectPath<'_>,
+) -> Option<zbus::names::UniqueName<'static>> {
+    let mut elements = session_handle.as_str().rsplit('/');
+    let sender = elements.nth(1)?;
+    let peer_name = format!(":{}", sender.replace('_', "."));
+    zbus::names::UniqueName::try_from(peer_name).ok()
+}
+
+async fn get_available_source_types(screencast_proxy: &dbus_proxy::muffin::ScreenCast<'_>) -> u32 {
+    let mut available_source_types = SOURCE_TYPE_MONITOR | SOURCE_TYPE_WINDOW;
+    match screencast_proxy.version().await {
+        Ok(version) if version >= MUFFIN_RECORD_VIRTUAL_VERSION => {
+            available_source_types |= SOURCE_TYPE_VIRTUAL;
+        }
+        Ok(version) => {
+            log::info!("Muffin ScreenCast API version {version} does not support virtual monitors")
+        }
+        Err(err) => log::warn!("Could not query the Muffin ScreenCast API version: {err}"),
+    }
+    available_source_types
+}
+
+pub(super) fn encode_streams(
+    pipewire_streams: Vec<(u32, HashMap<String, zvariant::OwnedValue>)>,
+) -> zbus::Result<zvariant::OwnedValue> {
+    let mut streams = zvariant::Array::new(&zvariant::Signature::from_str("(ua{sv})")?);
+    for (pipewire_stream_id, properties) in pipewire_streams {
+        let stream_info = zvariant::StructureBuilder::new()
+            .add_field(pipewire_stream_id)
+            .add_field(properties)
+            .build()?;
+        streams.append(stream_info.into())?;
+        log::debug!("ScreenCast started on PipeWire stream ID: {pipewire_stream_id}");
+    }
+    Ok(zvariant::OwnedValue::try_from(streams)?)
+}
+
+fn parse_restore_token(
+    options: &HashMap<String, zvariant::OwnedValue>,
+) -> Result<Option<String>, Error> {
+    if let Some(restore_token) = options.get("restore_token") {
+        return Ok(Some(restore_token.downcast_ref()?));
+    }
+    let Some(restore_data) = options.get("restore_data") else {
+        return Ok(None);
+    };
+    let restore_data = restore_data.downcast_ref::<zvariant::Structure>()?;
+    let [vendor, version, token] = restore_data.fields() else {
+        return Err(Error::InvalidArgument("malformed restore data".into()));
+    };
+    if vendor.downcast_ref::<&str>()? != RESTORE_DATA_VENDOR
+        || version.downcast_ref::<u32>()? != RESTORE_DATA_VERSION
+    {
+        log::warn!("Ignoring restore data issued by another portal implementation");
+        return Ok(None);
+    }
+    Ok(Some(token.downcast_ref()?))
+}
+
+fn encode_restore_token(token: String) -> zbus::Result<zvariant::OwnedValue> {
+    let restore_data = zvariant::StructureBuilder::new()
+        .add_field(RESTORE_DATA_VENDOR)
+        .add_field(RESTORE_DATA_VERSION)
+        .add_field(zvariant::Value::new(token))
+        .build()?;
+    Ok(zvariant::OwnedValue::try_from(zvariant::Value::from(
+        restore_data,
+    ))?)
+}
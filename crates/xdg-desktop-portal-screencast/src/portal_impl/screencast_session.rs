@@ -1,37 +1,70 @@
 use super::ScreenCastStream;
+use super::error::Error;
+use super::policy;
+use super::restore_store::{
+    PERSIST_MODE_NONE, PERSIST_MODE_PERSISTENT, PERSIST_MODE_TRANSIENT, RestoreData,
+};
+use super::screencast::{
+    CURSOR_TYPE_EMBEDDED, CURSOR_TYPE_HIDDEN, CURSOR_TYPE_METADATA, SOURCE_TYPE_MONITOR,
+    SOURCE_TYPE_VIRTUAL, SOURCE_TYPE_WINDOW,
+};
+use super::source_selector::{self, ConfiguredSourceSelector, SourceCatalog};
+use crate::config::PolicyAction;
 use crate::dbus_proxy;
-use libsourceselector::{SerdeJson, Source, Sources};
+use libsourceselector::{
+    CursorMode, DeviceRequest, Devices, PROTOCOL_VERSION, SelectorRequest, Source,
+};
 use std::collections::HashMap;
 use zbus::zvariant;
 
+pub struct SelectSourcesOptions {
+    pub source_types: u32,
+    pub multiple: bool,
+    pub cursor_mode: u32,
+    pub persist_mode: u32,
+    pub restore_data: Option<RestoreData>,
+    pub policy: PolicyAction,
+}
+
 pub struct ScreenCastSession<'a> {
     app_id: String,
-    session_proxy: dbus_proxy::xdg_desktop_portal::Session<'a>,
     screencast_session_proxy: dbus_proxy::muffin::ScreenCastSession<'a>,
-    screencast_stream: Option<ScreenCastStream<'a>>,
-    display_config_proxy: dbus_proxy::muffin::DisplayConfig<'a>,
-    window_proxy: dbus_proxy::muffin::Window<'a>,
+    remote_desktop_session_proxy: Option<dbus_proxy::muffin::RemoteDesktopSession<'a>>,
+    state: tokio::sync::Mutex<ScreenCastSessionState<'a>>,
+    closed: tokio::sync::watch::Sender<bool>,
+    source_catalog: SourceCatalog<'a>,
+}
+
+struct ScreenCastSessionState<'a> {
+    screencast_streams: Vec<ScreenCastStream<'a>>,
+    selected_sources: Vec<Source>,
+    persist_mode: u32,
+    stream_paths: HashMap<u32, zvariant::OwnedObjectPath>,
+    devices: u32,
 }
 
 impl<'a> ScreenCastSession<'a> {
-    pub async fn new<'b: 'a>(
+    pub async fn new(
         connection: zbus::Connection,
         app_id: String,
-        session_handle: &zvariant::ObjectPath<'b>,
         screencast_session_proxy: dbus_proxy::muffin::ScreenCastSession<'a>,
+        remote_desktop_session_proxy: Option<dbus_proxy::muffin::RemoteDesktopSession<'a>>,
     ) -> zbus::Result<Self> {
-        let session_proxy =
-            dbus_proxy::xdg_desktop_portal::Session::new(&connection, session_handle).await?;
-        let display_config_proxy = dbus_proxy::muffin::DisplayConfig::new(&connection).await?;
-        let window_proxy = dbus_proxy::muffin::Window::new(&connection).await?;
+        let source_catalog = SourceCatalog::new(&connection, ConfiguredSourceSelector).await?;
 
         Ok(Self {
             app_id,
-            session_proxy,
             screencast_session_proxy,
-            screencast_stream: None,
-            display_config_proxy,
-            window_proxy,
+            remote_desktop_session_proxy,
+            state: tokio::sync::Mutex::new(ScreenCastSessionState {
+                screencast_streams: Vec::new(),
+                selected_sources: Vec::new(),
+                persist_mode: PERSIST_MODE_NONE,
+                stream_paths: HashMap::new(),
+                devices: 0,
+            }),
+            closed: tokio::sync::watch::Sender::new(false),
+            source_catalog,
         })
     }
 
@@ -39,141 +72,297 @@ impl<'a> ScreenCastSession<'a> {
         &self.app_id
     }
 
-    pub async fn select_sources(&mut self) -> zbus::Result<()> {
-        let selected_source = self
-            .open_source_selector()
-            .await
-            .map_err(|err| zbus::Error::Failure(err.to_string()))?;
-        let screencast_stream_proxy = match selected_source {
-            Source::Monitor { monitor_name } => {
-                self.screencast_session_proxy
-                    .record_monitor(&monitor_name, HashMap::new())
-                    .await?
-            }
-            Source::Window { window_id, .. } => {
-                let window_id = window_id.into();
-                let properties = [("window-id", &window_id)].into();
-                self.screencast_session_proxy
-                    .record_window(properties)
-                    .await?
-            }
+    pub fn get_remote_desktop_session(
+        &self,
+    ) -> Option<&dbus_proxy::muffin::RemoteDesktopSession<'a>> {
+        self.remote_desktop_session_proxy.as_ref()
+    }
+
+    pub fn subscribe_closed(&self) -> tokio::sync::watch::Receiver<bool> {
+        self.closed.subscribe()
+    }
+
+    pub async fn get_devices(&self) -> u32 {
+        self.state.lock().await.devices
+    }
+
+    pub async fn select_devices(&self, devices: u32) {
+        self.state.lock().await.devices = devices;
+    }
+
+    pub async fn confirm_devices(&self, devices: Devices) -> Result<Devices, Error> {
+        let request = DeviceRequest {
+            app_id: self.app_id.clone(),
+            devices,
         };
-        self.screencast_stream = Some(ScreenCastStream::new(screencast_stream_proxy).await?);
-        Ok(())
+        let mut closed = self.closed.subscribe();
+        tokio::select! {
+            devices = self.source_catalog.confirm_devices(request) => devices,
+            _ = closed.wait_for(|x| *x) => Err(Error::Cancelled),
+        }
     }
 
-    async fn get_monitor_sources(&self) -> zbus::Result<Sources> {
-        let (_, _, outputs, _, _, _) = self.display_config_proxy.get_resources().await?;
-        let mut monitor_sources = Vec::new();
-        for output in outputs {
-            let (_, _, _, _, monitor_name, _, _, _) = output;
-            monitor_sources.push(Source::Monitor { monitor_name });
+    pub async fn get_stream_path(
+        &self,
+        pipewire_stream_id: u32,
+    ) -> Option<zvariant::OwnedObjectPath> {
+        self.state
+            .lock()
+            .await
+            .stream_paths
+            .get(&pipewire_stream_id)
+            .cloned()
+    }
+
+    pub async fn get_restore_data(&self) -> Option<(u32, RestoreData)> {
+        let state = self.state.lock().await;
+        if state.persist_mode == PERSIST_MODE_NONE || state.selected_sources.is_empty() {
+            return None;
         }
-        Ok(monitor_sources.into())
+        let restore_data = RestoreData {
+            sources: state.selected_sources.clone(),
+        };
+        // Muffin window ids are reused after a restart, so they cannot identify a window for good
+        let has_window = restore_data
+            .sources
+            .iter()
+            .any(|x| matches!(x, Source::Window { .. }));
+        let persist_mode = if state.persist_mode == PERSIST_MODE_PERSISTENT && has_window {
+            PERSIST_MODE_TRANSIENT
+        } else {
+            state.persist_mode
+        };
+        Some((persist_mode, restore_data))
     }
 
-    async fn get_window_sources(&self) -> zbus::Result<Sources> {
-        let windows = self.window_proxy.list_windows().await?;
-        let mut window_sources = Vec::new();
-        for window in windows {
-            let Some(Ok(window_id)) = window.get("id").map(|x| x.downcast_ref()) else {
-                log::warn!("Window id isn't valid integer or unavailable, skipping...");
-                continue;
+    pub async fn select_sources(&self, options: &SelectSourcesOptions) -> Result<bool, Error> {
+        let preselected_sources = match (&options.policy, &options.restore_data) {
+            (PolicyAction::Select { monitor, window }, _) => Some(
+                self.get_policy_sources(monitor.as_deref(), window.as_deref(), options)
+                    .await?,
+            ),
+            (_, Some(restore_data)) if self.can_restore(restore_data, options).await? => {
+                log::debug!("Restoring the previously selected sources");
+                Some(restore_data.sources.clone())
+            }
+            (_, Some(_)) => {
+                log::info!("The restored sources are no longer available, asking the user");
+                None
+            }
+            (_, None) => None,
+        };
+        let is_interactive = preselected_sources.is_none();
+        let (selected_sources, cursor_mode, persist_mode) = match preselected_sources {
+            Some(preselected_sources) => (
+                preselected_sources,
+                options.cursor_mode,
+                options.persist_mode,
+            ),
+            None => {
+                let mut cursor_modes = vec![CursorMode::Hidden];
+                if options.cursor_mode != CURSOR_TYPE_HIDDEN {
+                    cursor_modes.push(source_selector::to_cursor_mode(options.cursor_mode));
+                }
+                let request = SelectorRequest {
+                    version: PROTOCOL_VERSION,
+                    app_id: self.app_id.clone(),
+                    source_types: source_selector::to_source_types(options.source_types),
+                    multiple: options.multiple,
+                    cursor_modes,
+                    cursor_mode: source_selector::to_cursor_mode(options.cursor_mode),
+                    persist_mode: source_selector::to_persist_mode(options.persist_mode),
+                    sources: Vec::new().into(),
+                    thumbnails: Vec::new(),
+                };
+                let mut closed = self.closed.subscribe();
+                let open_source_selector = self.source_catalog.select_sources(request);
+                let selection = tokio::select! {
+                    selection = open_source_selector => selection?,
+                    _ = closed.wait_for(|x| *x) => {
+                        return Err(Error::Cancelled);
+                    }
+                };
+                (
+                    selection.sources.into(),
+                    source_selector::from_cursor_mode(selection.cursor_mode),
+                    source_selector::from_persist_mode(selection.persist_mode),
+                )
+            }
+        };
+        let cursor_mode = to_muffin_cursor_mode(cursor_mode).into();
+        let mut screencast_streams = Vec::new();
+        for selected_source in &selected_sources {
+            let (screencast_stream_proxy, source_type) = match selected_source {
+                Source::Monitor { monitor_name } => {
+                    let properties = [("cursor-mode", &cursor_mode)].into();
+                    let screencast_stream_proxy = self
+                        .screencast_session_proxy
+                        .record_monitor(monitor_name, properties)
+                        .await?;
+                    (screencast_stream_proxy, SOURCE_TYPE_MONITOR)
+                }
+                Source::Window { window_id, .. } => {
+                    let window_id = (*window_id).into();
+                    let properties =
+                        [("window-id", &window_id), ("cursor-mode", &cursor_mode)].into();
+                    let screencast_stream_proxy = self
+                        .screencast_session_proxy
+                        .record_window(properties)
+                        .await?;
+                    (screencast_stream_proxy, SOURCE_TYPE_WINDOW)
+                }
+                Source::Area {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    let properties = [("cursor-mode", &cursor_mode)].into();
+                    let screencast_stream_proxy = self
+                        .screencast_session_proxy
+                        .record_area(*x, *y, *width, *height, properties)
+                        .await?;
+                    (screencast_stream_proxy, SOURCE_TYPE_MONITOR)
+                }
+                Source::Virtual => {
+                    let properties = [("cursor-mode", &cursor_mode)].into();
+                    let screencast_stream_proxy = self
+                        .screencast_session_proxy
+                        .record_virtual(properties)
+                        .await?;
+                    (screencast_stream_proxy, SOURCE_TYPE_VIRTUAL)
+                }
             };
-            let window_name = window
-                .get("title")
-                .and_then(|x| x.downcast_ref().ok())
-                .unwrap_or_else(|| format!("<Unnamed Window: {window_id}>"));
-            let icon_path = window
-                .get("res_name")
-                .and_then(|x| x.downcast_ref().ok())
-                .and_then(get_icon_path);
-            window_sources.push(Source::Window {
-                window_id,
-                window_name,
-                icon_path,
+            screencast_streams
+                .push(ScreenCastStream::new(screencast_stream_proxy, source_type).await?);
+        }
+        let mut state = self.state.lock().await;
+        state.screencast_streams = screencast_streams;
+        state.selected_sources = selected_sources;
+        state.persist_mode = persist_mode;
+        Ok(is_interactive)
+    }
+
+    async fn get_policy_sources(
+        &self,
+        monitor: Option<&str>,
+        window: Option<&str>,
+        options: &SelectSourcesOptions,
+    ) -> Result<Vec<Source>, Error> {
+        let mut policy_sources = Vec::new();
+        if let Some(monitor) = monitor
+            && options.source_types & SOURCE_TYPE_MONITOR != 0
+        {
+            let monitor_sources = self.source_catalog.get_monitor_sources().await?;
+            let monitor_source = monitor_sources.iter().find(|x| match x {
+                Source::Monitor { monitor_name } => policy::glob_match(monitor, monitor_name),
+                _ => false,
             });
+            policy_sources.extend(monitor_source.cloned());
         }
-        Ok(window_sources.into())
+        if let Some(window) = window
+            && options.source_types & SOURCE_TYPE_WINDOW != 0
+        {
+            let window_sources = self.source_catalog.get_window_sources().await?;
+            let window_source = window_sources.iter().find(|x| match x {
+                Source::Window { window_name, .. } => policy::glob_match(window, window_name),
+                _ => false,
+            });
+            policy_sources.extend(window_source.cloned());
+        }
+        if policy_sources.is_empty() {
+            return Err(Error::NotFound("no source matches the policy rule".into()));
+        }
+        if !options.multiple {
+            policy_sources.truncate(1);
+        }
+        log::debug!("Selecting the sources chosen by policy");
+        Ok(policy_sources)
     }
 
-    async fn open_source_selector(&self) -> Result<Source, Box<dyn std::error::Error>> {
-        let mut exe = std::env::current_exe()?;
-        exe.set_file_name("sourceselector-ui");
-        let monitor_sources = self.get_monitor_sources().await?;
-        let window_sources = self.get_window_sources().await?;
-        let output = tokio::process::Command::new(exe)
-            .arg(monitor_sources.to_json()?)
-            .arg(window_sources.to_json()?)
-            .stdout(std::process::Stdio::piped())
-            .spawn()?
-            .wait_with_output()
-            .await?;
-        let stdout = String::from_utf8(output.stdout)?;
-        if stdout.is_empty() {
-            return Err("sourceselector-ui did not return the answer".into());
+    async fn can_restore(
+        &self,
+        restore_data: &RestoreData,
+        options: &SelectSourcesOptions,
+    ) -> zbus::Result<bool> {
+        if restore_data.sources.is_empty() || (!options.multiple && restore_data.sources.len() > 1)
+        {
+            return Ok(false);
         }
-        let selected_source = Source::from_json(&stdout)?;
-        Ok(selected_source)
+        let monitor_sources = self.source_catalog.get_monitor_sources().await?;
+        let window_sources = self.source_catalog.get_window_sources().await?;
+        let is_available =
+            |source: &Source| match source {
+                Source::Monitor { .. } => {
+                    options.source_types & SOURCE_TYPE_MONITOR != 0
+                        && monitor_sources.iter().any(|x| x == source)
+                }
+                Source::Window { window_id, .. } => options.source_types & SOURCE_TYPE_WINDOW != 0
+                    && window_sources.iter().any(
+                        |x| matches!(x, Source::Window { window_id: id, .. } if id == window_id),
+                    ),
+                Source::Area { .. } => options.source_types & SOURCE_TYPE_MONITOR != 0,
+                Source::Virtual => options.source_types & SOURCE_TYPE_VIRTUAL != 0,
+            };
+        Ok(restore_data.sources.iter().all(is_available))
     }
 
-    pub async fn start(&self) -> zbus::Result<u32> {
-        let screencast_stream = self.screencast_stream.as_ref().ok_or_else(|| {
-            zbus::Error::Failure(
+    pub async fn start(&self) -> Result<Vec<(u32, HashMap<String, zvariant::OwnedValue>)>, Error> {
+        let mut state = self.state.lock().await;
+        if state.screencast_streams.is_empty() {
+            if let Some(remote_desktop_session_proxy) = &self.remote_desktop_session_proxy {
+                remote_desktop_session_proxy.start().await?;
+                return Ok(Vec::new());
+            }
+            return Err(Error::Failed(
                 "ScreenCastStream must be created before waiting for its PipeWire stream".into(),
-            )
-        })?;
-        let session_start = self.screencast_session_proxy.start();
-        let wait_for_pipewire_stream = screencast_stream.wait_for_pipewire_stream();
-        let results = tokio::join!(session_start, wait_for_pipewire_stream);
+            ));
+        }
+        let session_start = async {
+            match &self.remote_desktop_session_proxy {
+                Some(remote_desktop_session_proxy) => remote_desktop_session_proxy.start().await,
+                None => self.screencast_session_proxy.start().await,
+            }
+        };
+        let wait_for_pipewire_streams = futures_util::future::try_join_all(
+            state
+                .screencast_streams
+                .iter()
+                .map(|x| x.wait_for_pipewire_stream()),
+        );
+        let results = tokio::join!(session_start, wait_for_pipewire_streams);
         results.0?;
-        results.1
+        let mut streams = Vec::new();
+        let mut stream_paths = HashMap::new();
+        for (index, (screencast_stream, pipewire_stream_id)) in
+            state.screencast_streams.iter().zip(results.1?).enumerate()
+        {
+            let properties = screencast_stream
+                .get_stream_properties(index.to_string())
+                .await?;
+            streams.push((pipewire_stream_id, properties));
+            stream_paths.insert(
+                pipewire_stream_id,
+                screencast_stream.get_object_path().to_owned().into(),
+            );
+        }
+        state.stream_paths = stream_paths;
+        Ok(streams)
     }
 
     pub async fn close(&self) {
-        _ = self.screencast_session_proxy.stop().await;
-        _ = self.session_proxy.close().await;
-    }
-}
-
-fn get_icon_path(app_id: &str) -> Option<String> {
-    static XDG_DATA_HOME: std::sync::LazyLock<String> =
-        std::sync::LazyLock::new(|| match get_xdg_data_home() {
-            Ok(dir) => dir,
-            Err(err) => {
-                log::warn!("Could not determine the $XDG_DATA_HOME directory: {err}");
-                log::warn!("User *.desktop files will not be looked up");
-                "".into()
-            }
-        });
-    let search_paths: [&'static str; 3] = [&XDG_DATA_HOME, "/usr/local/share", "/usr/share"];
-    for search_path in search_paths {
-        if search_path.is_empty() {
-            continue;
+        self.closed.send_replace(true);
+        match &self.remote_desktop_session_proxy {
+            Some(remote_desktop_session_proxy) => _ = remote_desktop_session_proxy.stop().await,
+            None => _ = self.screencast_session_proxy.stop().await,
         }
-        let path = format!("{search_path}/applications/{app_id}.desktop");
-        let desktop_file = match ini::Ini::load_from_file(&path) {
-            Ok(ini) => ini,
-            Err(ini::Error::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => continue,
-            Err(err) => {
-                log::error!("Could not read '{path}': {err}");
-                return None;
-            }
-        };
-        let icon_name = desktop_file.section(Some("Desktop Entry"))?.get("Icon")?;
-        let icon_path = freedesktop_icons::lookup(icon_name).find()?;
-        return Some(format!("file://{}", icon_path.to_str()?));
     }
-    None
 }
 
-fn get_xdg_data_home() -> Result<String, Box<dyn std::error::Error>> {
-    match std::env::var("XDG_DATA_HOME") {
-        Ok(xdg_data_home) => Ok(xdg_data_home),
-        Err(std::env::VarError::NotPresent) => match std::env::var("HOME") {
-            Ok(home) => Ok(format!("{home}/.local/share")),
-            Err(err) => Err(format!("Failed to lookup user's home directory: {err}").into()),
-        },
-        Err(err) => Err(format!("Invalid $XDG_DATA_HOME variable: {err}").into()),
+fn to_muffin_cursor_mode(cursor_mode: u32) -> u32 {
+    match cursor_mode {
+        CURSOR_TYPE_EMBEDDED => 1,
+        CURSOR_TYPE_METADATA => 2,
+        _ => 0,
     }
 }
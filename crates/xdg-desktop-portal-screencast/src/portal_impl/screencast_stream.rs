@@ -1,39 +1,78 @@
-use crate::dbus_proxy;
+use super::error::Error;
+use crate::{config, dbus_proxy};
 use futures_util::StreamExt;
+use std::collections::HashMap;
+use zbus::zvariant;
 
 pub struct ScreenCastStream<'a> {
     screencast_stream_proxy: dbus_proxy::muffin::ScreenCastStream<'a>,
+    source_type: u32,
 }
 
 impl<'a> ScreenCastStream<'a> {
     pub async fn new(
         screencast_stream_proxy: dbus_proxy::muffin::ScreenCastStream<'a>,
+        source_type: u32,
     ) -> zbus::Result<Self> {
         Ok(Self {
             screencast_stream_proxy,
+            source_type,
         })
     }
 
-    pub async fn wait_for_pipewire_stream(&self) -> zbus::Result<u32> {
+    pub fn get_object_path(&self) -> &zvariant::ObjectPath<'_> {
+        self.screencast_stream_proxy.inner().path()
+    }
+
+    pub async fn get_stream_properties(
+        &self,
+        id: String,
+    ) -> zbus::Result<HashMap<String, zvariant::OwnedValue>> {
+        let mut properties = HashMap::from([
+            ("id".into(), zvariant::Value::from(id).try_into()?),
+            ("source_type".into(), self.source_type.into()),
+        ]);
+        let parameters = match self.screencast_stream_proxy.parameters().await {
+            Ok(parameters) => parameters,
+            Err(err) => {
+                log::warn!("Could not get the stream parameters: {err}");
+                return Ok(properties);
+            }
+        };
+        for (parameter, property) in [
+            ("position", "position"),
+            ("size", "size"),
+            ("mapping-id", "mapping_id"),
+        ] {
+            if let Some(value) = parameters.get(parameter) {
+                properties.insert(property.into(), value.try_clone()?);
+            }
+        }
+        Ok(properties)
+    }
+
+    pub async fn wait_for_pipewire_stream(&self) -> Result<u32, Error> {
         let mut stream = self
             .screencast_stream_proxy
             .receive_pipewire_stream_added()
             .await?;
         let get_signal = async {
             stream.next().await.ok_or_else(|| {
-                zbus::Error::Failure(
+                Error::Failed(
                     "The stream was closed before the 'PipeWireStreamAdded' is signaled".into(),
                 )
             })
         };
-        let timeout = tokio::time::sleep(std::time::Duration::from_secs(1));
+        let timeout = tokio::time::sleep(std::time::Duration::from_millis(
+            config::get().timeouts.pipewire_stream_ms,
+        ));
         tokio::select! {
             signal = get_signal => {
                 let pipewire_stream_id = signal?.message().body().deserialize::<u32>()?;
                 Ok(pipewire_stream_id)
             }
             _ = timeout => {
-                Err(zbus::Error::Failure("The stream was timed out before the 'PipeWireStreamAdded' is signaled".into()))
+                Err(Error::Failed("The stream was timed out before the 'PipeWireStreamAdded' is signaled".into()))
             }
         }
     }
@@ -0,0 +1,303 @@
+use super::Request;
+use super::error::Error;
+use super::policy;
+use super::screencast::{SOURCE_TYPE_MONITOR, SOURCE_TYPE_WINDOW};
+use super::source_selector::{self, ConfiguredSourceSelector, SourceCatalog};
+use crate::config::{self, PolicyAction};
+use crate::dbus_proxy;
+use libsourceselector::{CursorMode, PROTOCOL_VERSION, PersistMode, SelectorRequest, Source};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+use zbus::zvariant;
+
+#[derive(Clone)]
+pub struct Screenshot {
+    inner: Arc<ScreenshotInner<'static>>,
+}
+
+impl Screenshot {
+    pub async fn new(connection: zbus::Connection) -> zbus::Result<Self> {
+        let inner = Arc::new(ScreenshotInner::new(connection).await?);
+
+        Ok(Self { inner })
+    }
+
+    pub async fn on_compositor_appeared(&self) -> zbus::Result<()> {
+        self.inner.on_compositor_appeared().await
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.Screenshot")]
+impl Screenshot {
+    async fn screenshot(
+        &self,
+        handle: zvariant::OwnedObjectPath,
+        app_id: String,
+        parent_window: String,
+        options: HashMap<String, zvariant::OwnedValue>,
+    ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        log::debug!("Screenshot():");
+        log::debug!("\thandle: {handle}");
+        log::debug!("\tapp_id: {app_id}");
+        log::debug!("\tparent_window: {parent_window}");
+        log::debug!("\toptions: {options:?}");
+
+        self.inner
+            .screenshot(handle, app_id, parent_window, options)
+            .await
+    }
+
+    async fn pick_color(
+        &self,
+        handle: zvariant::OwnedObjectPath,
+        app_id: String,
+        parent_window: String,
+        options: HashMap<String, zvariant::OwnedValue>,
+    ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        log::debug!("PickColor():");
+        log::debug!("\thandle: {handle}");
+        log::debug!("\tapp_id: {app_id}");
+        log::debug!("\tparent_window: {parent_window}");
+        log::debug!("\toptions: {options:?}");
+
+        self.inner
+            .pick_color(handle, app_id, parent_window, options)
+            .await
+    }
+
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        ScreenshotInner::version()
+    }
+}
+
+struct ScreenshotInner<'a> {
+    connection: zbus::Connection,
+    screenshot_proxy: RwLock<dbus_proxy::cinnamon::Screenshot<'a>>,
+    source_catalog: RwLock<Arc<SourceCatalog<'a>>>,
+}
+
+impl<'a> ScreenshotInner<'a> {
+    async fn new(connection: zbus::Connection) -> zbus::Result<Self> {
+        let screenshot_proxy = dbus_proxy::cinnamon::Screenshot::new(&connection).await?;
+        let source_catalog = SourceCatalog::new(&connection, ConfiguredSourceSelector).await?;
+
+        Ok(Self {
+            connection,
+            screenshot_proxy: RwLock::new(screenshot_proxy),
+            source_catalog: RwLock::new(Arc::new(source_catalog)),
+        })
+    }
+
+    async fn on_compositor_appeared(&self) -> zbus::Result<()> {
+        let screenshot_proxy = dbus_proxy::cinnamon::Screenshot::new(&self.connection).await?;
+        let source_catalog = SourceCatalog::new(&self.connection, ConfiguredSourceSelector).await?;
+        *self.screenshot_proxy.write().unwrap() = screenshot_proxy;
+        *self.source_catalog.write().unwrap() = Arc::new(source_catalog);
+        Ok(())
+    }
+
+    async fn screenshot(
+        &self,
+        handle: zvariant::OwnedObjectPath,
+        app_id: String,
+        _parent_window: String,
+        options: HashMap<String, zvariant::OwnedValue>,
+    ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        let connection = self.connection.clone();
+        let body = async {
+            if let PolicyAction::Deny = policy::evaluate(&app_id) {
+                return Err(Error::Denied(format!("'{app_id}' is denied by policy")));
+            }
+            let interactive = match options.get("interactive") {
+                Some(interactive) => interactive.downcast_ref::<bool>()?,
+                None => false,
+            };
+            let permission_store_checked = match options.get("permission_store_checked") {
+                Some(permission_store_checked) => {
+                    permission_store_checked.downcast_ref::<bool>()?
+                }
+                None => false,
+            };
+            let filename = format!(
+                "{}/Screenshot-{}.png",
+                get_xdg_pictures_dir()?,
+                std::time::UNIX_EPOCH
+                    .elapsed()
+                    .map_err(|err| Error::Failed(err.to_string()))?
+                    .as_millis()
+            );
+            let source = if interactive || !permission_store_checked {
+                let request = SelectorRequest {
+                    version: PROTOCOL_VERSION,
+                    app_id: app_id.clone(),
+                    source_types: source_selector::to_source_types(
+                        SOURCE_TYPE_MONITOR | SOURCE_TYPE_WINDOW,
+                    ),
+                    multiple: false,
+                    cursor_modes: vec![CursorMode::Hidden],
+                    cursor_mode: CursorMode::Hidden,
+                    persist_mode: PersistMode::None,
+                    sources: Vec::new().into(),
+                    thumbnails: Vec::new(),
+                };
+                let source_catalog = self.source_catalog.read().unwrap().clone();
+                let selection = source_catalog.select_sources(request).await?;
+                Vec::from(selection.sources).into_iter().next()
+            } else {
+                None
+            };
+            self.take_screenshot(source.as_ref(), true, &filename)
+                .await?;
+            Ok::<_, Error>(HashMap::from([(
+                "uri".into(),
+                zvariant::Value::from(format!("file://{filename}")).try_into()?,
+            )]))
+        };
+        match Request::run(&connection, &handle, body).await {
+            Ok(results) => {
+                _ = Request::emit_response(&connection, &handle, 0, HashMap::new()).await;
+                (0, results)
+            }
+            Err(Error::Closed) => (1, HashMap::new()),
+            Err(err) => {
+                log::error!("screenshot(): {err}");
+                let response = err.response();
+                _ = Request::emit_response(&connection, &handle, response, HashMap::new()).await;
+                (response, HashMap::new())
+            }
+        }
+    }
+
+    async fn pick_color(
+        &self,
+        handle: zvariant::OwnedObjectPath,
+        app_id: String,
+        _parent_window: String,
+        _options: HashMap<String, zvariant::OwnedValue>,
+    ) -> (u32, HashMap<String, zvariant::OwnedValue>) {
+        let connection = self.connection.clone();
+        let body = async {
+            if let PolicyAction::Deny = policy::evaluate(&app_id) {
+                return Err(Error::Denied(format!("'{app_id}' is denied by policy")));
+            }
+            let filename = format!(
+                "{}/xdg-desktop-portal-screencast-{}.png",
+                source_selector::get_xdg_runtime_dir(),
+                uuid::Uuid::new_v4()
+            );
+            self.take_screenshot(None, false, &filename).await?;
+            let source_catalog = self.source_catalog.read().unwrap().clone();
+            let color = source_catalog.pick_color(&filename).await;
+            _ = std::fs::remove_file(&filename);
+            let color = color?;
+            Ok::<_, Error>(HashMap::from([(
+                "color".into(),
+                zvariant::Value::from((color.red, color.green, color.blue)).try_into()?,
+            )]))
+        };
+        match Request::run(&connection, &handle, body).await {
+            Ok(results) => {
+                _ = Request::emit_response(&connection, &handle, 0, HashMap::new()).await;
+                (0, results)
+            }
+            Err(Error::Closed) => (1, HashMap::new()),
+            Err(err) => {
+                log::error!("pick_color(): {err}");
+                let response = err.response();
+                _ = Request::emit_response(&connection, &handle, response, HashMap::new()).await;
+                (response, HashMap::new())
+            }
+        }
+    }
+
+    async fn take_screenshot(
+        &self,
+        source: Option<&Source>,
+        flash: bool,
+        filename: &str,
+    ) -> Result<(), Error> {
+        let screenshot_proxy = self.screenshot_proxy.read().unwrap().clone();
+        let source_catalog = self.source_catalog.read().unwrap().clone();
+        match source {
+            Some(Source::Monitor { monitor_name }) => {
+                let (x, y, width, height) = source_catalog
+                    .get_monitor_geometry(monitor_name)
+                    .await?
+                    .ok_or_else(|| {
+                        Error::NotFound(format!("monitor '{monitor_name}' is not active"))
+                    })?;
+                screenshot_proxy
+                    .screenshot_area(false, x, y, width, height, flash, filename)
+                    .await?
+            }
+            Some(Source::Area {
+                x,
+                y,
+                width,
+                height,
+            }) => {
+                screenshot_proxy
+                    .screenshot_area(false, *x, *y, *width, *height, flash, filename)
+                    .await?
+            }
+            Some(Source::Window { window_id, .. }) => {
+                let (x, y, width, height) = source_catalog
+                    .get_window_geometry(*window_id)
+                    .await?
+                    .ok_or_else(|| {
+                    Error::NotFound(format!("window {window_id} is not open"))
+                })?;
+                screenshot_proxy
+                    .screenshot_area(false, x, y, width, height, flash, filename)
+                    .await?
+            }
+            Some(Source::Virtual) => {
+                return Err(Error::InvalidArgument(
+                    "a virtual monitor cannot be captured".into(),
+                ));
+            }
+            None => screenshot_proxy.screenshot(false, flash, filename).await?,
+        }
+        Ok(())
+    }
+
+    fn version() -> u32 {
+        2
+    }
+}
+
+fn get_xdg_pictures_dir() -> Result<String, Error> {
+    let home = std::env::var("HOME")
+        .map_err(|err| Error::Failed(format!("Failed to lookup user's home directory: {err}")))?;
+    let user_dirs = match config::get_xdg_dir("XDG_CONFIG_HOME", ".config") {
+        Ok(xdg_config_home) => std::fs::read_to_string(format!("{xdg_config_home}/user-dirs.dirs")),
+        Err(err) => {
+            log::warn!("Could not determine the $XDG_CONFIG_HOME directory: {err}");
+            Ok(String::new())
+        }
+    };
+    let xdg_pictures_dir = user_dirs
+        .ok()
+        .and_then(|x| parse_user_dir(&x, "XDG_PICTURES_DIR", &home))
+        .unwrap_or_else(|| format!("{home}/Pictures"));
+    std::fs::create_dir_all(&xdg_pictures_dir)?;
+    Ok(xdg_pictures_dir)
+}
+
+fn parse_user_dir(user_dirs: &str, name: &str, home: &str) -> Option<String> {
+    user_dirs.lines().find_map(|line| {
+        let value = line
+            .trim()
+            .strip_prefix(name)?
+            .trim_start()
+            .strip_prefix('=')?;
+        let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+        match value.strip_prefix("$HOME") {
+            Some(path) => Some(format!("{home}{path}")),
+            None => value.starts_with('/').then(|| value.to_owned()),
+        }
+    })
+}
@@ -0,0 +1,36 @@
+use super::ScreenCast;
+use zbus::zvariant;
+
+pub struct Session {
+    screencast_ctx: ScreenCast,
+    session_handle: zvariant::OwnedObjectPath,
+}
+
+impl Session {
+    pub fn new(screencast_ctx: ScreenCast, session_handle: zvariant::OwnedObjectPath) -> Self {
+        Self {
+            screencast_ctx,
+            session_handle,
+        }
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.Session")]
+impl Session {
+    async fn close(&self) {
+        log::debug!("Close():");
+        log::debug!("\tsession_handle: {}", self.session_handle);
+
+        self.screencast_ctx
+            .close_session(&self.session_handle)
+            .await
+    }
+
+    #[zbus(signal)]
+    pub async fn closed(emitter: &zbus::object_server::SignalEmitter<'_>) -> zbus::Result<()>;
+
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        1
+    }
+}
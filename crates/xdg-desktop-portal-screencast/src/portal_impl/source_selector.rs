@@ -0,0 +1,710 @@
+use super::error::Error;
+use super::restore_store::{PERSIST_MODE_NONE, PERSIST_MODE_PERSISTENT, PERSIST_MODE_TRANSIENT};
+use super::screencast::{
+    CURSOR_TYPE_EMBEDDED, CURSOR_TYPE_HIDDEN, CURSOR_TYPE_METADATA, SOURCE_TYPE_MONITOR,
+    SOURCE_TYPE_VIRTUAL, SOURCE_TYPE_WINDOW,
+};
+use crate::{config, dbus_proxy};
+use libsourceselector::client::{ProcessSourceSelector, SelectorError, SourceSelector};
+use libsourceselector::{
+    Color, CursorMode, DeviceRequest, Devices, PersistMode, Selection, SelectorRequest, Source,
+    SourceTypes, Sources, Thumbnail,
+};
+
+const THUMBNAIL_SIZE: (u32, u32) = (320, 180);
+
+type Geometry = (i32, i32, i32, i32);
+
+pub struct SourceCatalog<'a, S = ConfiguredSourceSelector> {
+    display_config_proxy: dbus_proxy::muffin::DisplayConfig<'a>,
+    window_proxy: dbus_proxy::muffin::Window<'a>,
+    screenshot_proxy: dbus_proxy::cinnamon::Screenshot<'a>,
+    source_selector: S,
+}
+
+pub struct ConfiguredSourceSelector;
+
+impl SourceSelector for ConfiguredSourceSelector {
+    async fn select(&self, request: SelectorRequest) -> Result<Selection, SelectorError> {
+        let source_selector = ProcessSourceSelector::new(get_source_selector_exe()?);
+        source_selector.select(request).await
+    }
+
+    async fn pick_color(&self, image_path: &str) -> Result<Color, SelectorError> {
+        let source_selector = ProcessSourceSelector::new(get_source_selector_exe()?);
+        source_selector.pick_color(image_path).await
+    }
+
+    async fn confirm_devices(&self, request: DeviceRequest) -> Result<Devices, SelectorError> {
+        let source_selector = ProcessSourceSelector::new(get_source_selector_exe()?);
+        source_selector.confirm_devices(request).await
+    }
+}
+
+struct Thumbnails(Vec<Thumbnail>);
+
+impl Drop for Thumbnails {
+    fn drop(&mut self) {
+        for thumbnail in &self.0 {
+            _ = std::fs::remove_file(&thumbnail.path);
+        }
+    }
+}
+
+impl<'a, S: SourceSelector> SourceCatalog<'a, S> {
+    pub async fn new(connection: &zbus::Connection, source_selector: S) -> zbus::Result<Self> {
+        let display_config_proxy = dbus_proxy::muffin::DisplayConfig::new(connection).await?;
+        let window_proxy = dbus_proxy::muffin::Window::new(connection).await?;
+        let screenshot_proxy = dbus_proxy::cinnamon::Screenshot::new(connection).await?;
+
+        Ok(Self {
+            display_config_proxy,
+            window_proxy,
+            screenshot_proxy,
+            source_selector,
+        })
+    }
+
+    pub async fn get_monitor_sources(&self) -> zbus::Result<Sources> {
+        let monitors = self.list_monitors().await?;
+        Ok(monitors
+            .into_iter()
+            .map(|(source, _)| source)
+            .collect::<Vec<_>>()
+            .into())
+    }
+
+    pub async fn get_window_sources(&self) -> zbus::Result<Sources> {
+        let windows = self.list_windows().await?;
+        Ok(windows
+            .into_iter()
+            .map(|(source, _)| source)
+            .collect::<Vec<_>>()
+            .into())
+    }
+
+    async fn list_monitors(&self) -> zbus::Result<Vec<(Source, Option<Geometry>)>> {
+        let (_, crtcs, outputs, _, _, _) = self.display_config_proxy.get_resources().await?;
+        let mut monitors = Vec::new();
+        for output in outputs {
+            let (_, _, current_crtc, _, monitor_name, _, _, _) = output;
+            let geometry = crtcs
+                .iter()
+                .find(|x| i64::from(x.0) == i64::from(current_crtc))
+                .map(|&(_, _, x, y, width, height, _, _, _, _)| (x, y, width, height));
+            monitors.push((Source::Monitor { monitor_name }, geometry));
+        }
+        Ok(monitors)
+    }
+
+    async fn list_windows(&self) -> zbus::Result<Vec<(Source, Option<Geometry>)>> {
+        let windows = self.window_proxy.list_windows().await?;
+        let mut window_sources = Vec::new();
+        for window in windows {
+            let Some(Ok(window_id)) = window.get("id").map(|x| x.downcast_ref()) else {
+                log::warn!("Window id isn't valid integer or unavailable, skipping...");
+                continue;
+            };
+            let window_name = window
+                .get("title")
+                .and_then(|x| x.downcast_ref().ok())
+                .unwrap_or_else(|| format!("<Unnamed Window: {window_id}>"));
+            let icon_path = window
+                .get("res_name")
+                .and_then(|x| x.downcast_ref().ok())
+                .and_then(get_icon_path);
+            let get_i32 = |key| window.get(key).and_then(|x| x.downcast_ref::<i32>().ok());
+            let geometry = match (
+                get_i32("x"),
+                get_i32("y"),
+                get_i32("width"),
+                get_i32("height"),
+            ) {
+                (Some(x), Some(y), Some(width), Some(height)) => Some((x, y, width, height)),
+                _ => None,
+            };
+            let source = Source::Window {
+                window_id,
+                window_name,
+                icon_path,
+            };
+            window_sources.push((source, geometry));
+        }
+        Ok(window_sources)
+    }
+
+    pub async fn select_sources(&self, mut request: SelectorRequest) -> Result<Selection, Error> {
+        let mut sources = Vec::new();
+        if request.source_types.monitor {
+            sources.extend(self.list_monitors().await?);
+        }
+        if request.source_types.window {
+            sources.extend(self.list_windows().await?);
+        }
+        if request.source_types.virtual_monitor {
+            sources.push((Source::Virtual, None));
+        }
+        let (thumbnails, geometries): (Vec<_>, Vec<_>) = if config::get().selector.thumbnails {
+            let thumbnails = sources.iter().filter_map(|(source, geometry)| {
+                let geometry = (*geometry)?;
+                let thumbnail = Thumbnail {
+                    source: source.clone(),
+                    path: format!(
+                        "{}/xdg-desktop-portal-screencast-{}.png",
+                        get_xdg_runtime_dir(),
+                        uuid::Uuid::new_v4()
+                    ),
+                };
+                Some((thumbnail, geometry))
+            });
+            thumbnails.unzip()
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        let thumbnails = Thumbnails(thumbnails);
+        request.sources = sources
+            .into_iter()
+            .map(|(source, _)| source)
+            .collect::<Vec<_>>()
+            .into();
+        request.thumbnails = thumbnails.0.clone();
+        let (selection, ()) = tokio::join!(
+            self.source_selector.select(request),
+            self.take_thumbnails(&thumbnails.0, &geometries)
+        );
+        Ok(selection?)
+    }
+
+    async fn take_thumbnails(&self, thumbnails: &[Thumbnail], geometries: &[Geometry]) {
+        let take_source_thumbnail = async |(thumbnail, geometry): (&Thumbnail, &Geometry)| {
+            if let Err(err) =
+                take_thumbnail(&self.screenshot_proxy, *geometry, &thumbnail.path).await
+            {
+                log::warn!("Could not take a thumbnail: {err}");
+            }
+        };
+        let thumbnails = thumbnails.iter().zip(geometries);
+        futures_util::future::join_all(thumbnails.map(take_source_thumbnail)).await;
+    }
+
+    pub async fn get_monitor_geometry(&self, monitor_name: &str) -> zbus::Result<Option<Geometry>> {
+        let monitors = self.list_monitors().await?;
+        let geometry = monitors
+            .into_iter()
+            .find_map(|(source, geometry)| match source {
+                Source::Monitor { monitor_name: name } if name == monitor_name => geometry,
+                _ => None,
+            });
+        Ok(geometry)
+    }
+
+    pub async fn get_window_geometry(&self, window_id: u64) -> zbus::Result<Option<Geometry>> {
+        let windows = self.list_windows().await?;
+        let geometry = windows
+            .into_iter()
+            .find_map(|(source, geometry)| match source {
+                Source::Window { window_id: id, .. } if id == window_id => geometry,
+                _ => None,
+            });
+        Ok(geometry)
+    }
+
+    pub async fn pick_color(&self, image_path: &str) -> Result<Color, Error> {
+        Ok(self.source_selector.pick_color(image_path).await?)
+    }
+
+    pub async fn confirm_devices(&self, request: DeviceRequest) -> Result<Devices, Error> {
+        Ok(self.source_selector.confirm_devices(request).await?)
+    }
+}
+
+async fn take_thumbnail(
+    screenshot_proxy: &dbus_proxy::cinnamon::Screenshot<'_>,
+    (x, y, width, height): Geometry,
+    path: &str,
+) -> Result<(), Error> {
+    let capture_path = format!("{path}.capture.png");
+    let result = async {
+        screenshot_proxy
+            .screenshot_area(false, x, y, width, height, false, &capture_path)
+            .await?;
+        let (capture_path, path) = (capture_path.clone(), path.to_owned());
+        tokio::task::spawn_blocking(move || scale_thumbnail(&capture_path, &path))
+            .await
+            .map_err(|err| Error::Failed(err.to_string()))?
+    }
+    .await;
+    _ = std::fs::remove_file(&capture_path);
+    result
+}
+
+fn scale_thumbnail(capture_path: &str, path: &str) -> Result<(), Error> {
+    let image = image::open(capture_path).map_err(|err| Error::Failed(err.to_string()))?;
+    let (width, height) = THUMBNAIL_SIZE;
+    let partial_path = format!("{path}.partial");
+    let saved = image
+        .thumbnail(width, height)
+        .save_with_format(&partial_path, image::ImageFormat::Png);
+    if let Err(err) = saved {
+        _ = std::fs::remove_file(&partial_path);
+        return Err(Error::Failed(err.to_string()));
+    }
+    std::fs::rename(&partial_path, path)?;
+    Ok(())
+}
+
+pub fn get_xdg_runtime_dir() -> String {
+    std::env::var("XDG_RUNTIME_DIR")
+        .unwrap_or_else(|_| std::env::temp_dir().to_string_lossy().into_owned())
+}
+
+fn get_source_selector_exe() -> std::io::Result<std::path::PathBuf> {
+    if let Some(command) = &config::get().selector.command {
+        return Ok(command.into());
+    }
+    let mut exe = std::env::current_exe()?;
+    exe.set_file_name("sourceselector-ui");
+    Ok(exe)
+}
+
+pub fn to_source_types(source_types: u32) -> SourceTypes {
+    SourceTypes {
+        monitor: source_types & SOURCE_TYPE_MONITOR != 0,
+        window: source_types & SOURCE_TYPE_WINDOW != 0,
+        area: source_types & SOURCE_TYPE_MONITOR != 0,
+        virtual_monitor: source_types & SOURCE_TYPE_VIRTUAL != 0,
+    }
+}
+
+pub fn to_cursor_mode(cursor_mode: u32) -> CursorMode {
+    match cursor_mode {
+        CURSOR_TYPE_EMBEDDED => CursorMode::Embedded,
+        CURSOR_TYPE_METADATA => CursorMode::Metadata,
+        _ => CursorMode::Hidden,
+    }
+}
+
+pub fn from_cursor_mode(cursor_mode: CursorMode) -> u32 {
+    match cursor_mode {
+        CursorMode::Hidden => CURSOR_TYPE_HIDDEN,
+        CursorMode::Embedded => CURSOR_TYPE_EMBEDDED,
+        CursorMode::Metadata => CURSOR_TYPE_METADATA,
+    }
+}
+
+pub fn to_persist_mode(persist_mode: u32) -> PersistMode {
+    match persist_mode {
+        PERSIST_MODE_TRANSIENT => PersistMode::Transient,
+        PERSIST_MODE_PERSISTENT => PersistMode::Persistent,
+        _ => PersistMode::None,
+    }
+}
+
+pub fn from_persist_mode(persist_mode: PersistMode) -> u32 {
+    match persist_mode {
+        PersistMode::None => PERSIST_MODE_NONE,
+        PersistMode::Transient => PERSIST_MODE_TRANSIENT,
+        PersistMode::Persistent => PERSIST_MODE_PERSISTENT,
+    }
+}
+
+fn get_icon_path(app_id: &str) -> Option<String> {
+    static XDG_DATA_HOME: std::sync::LazyLock<String> =
+        std::sync::LazyLock::new(
+            || match config::get_xdg_dir("XDG_DATA_HOME", ".local/share") {
+                Ok(dir) => dir,
+                Err(err) => {
+                    log::warn!("Could not determine the $XDG_DATA_HOME directory: {err}");
+                    log::warn!("User *.desktop files will not be looked up");
+                    "".into()
+                }
+            },
+        );
+    let config = config::get();
+    let default_search_paths = [&*XDG_DATA_HOME, "/usr/local/share", "/usr/share"];
+    let search_paths: Vec<&str> = match &config.icons.search_paths {
+        Some(search_paths) => search_paths.iter().map(|x| x.as_str()).collect(),
+        None => default_search_paths.to_vec(),
+    };
+    for search_path in search_paths {
+        if search_path.is_empty() {
+            continue;
+        }
+        let path = format!("{search_path}/applications/{app_id}.desktop");
+        let desktop_file = match ini::Ini::load_from_file(&path) {
+            Ok(ini) => ini,
+            Err(ini::Error::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                log::error!("Could not read '{path}': {err}");
+                return None;
+            }
+        };
+        let icon_name = desktop_file.section(Some("Desktop Entry"))?.get("Icon")?;
+        let icon_path = freedesktop_icons::lookup(icon_name).find()?;
+        return Some(format!("file://{}", icon_path.to_str()?));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Geometry, SourceCatalog, THUMBNAIL_SIZE, take_thumbnail};
+    use crate::dbus_proxy;
+    use crate::portal_impl::error::Error;
+    use libsourceselector::client::{SelectorError, SourceSelector};
+    use libsourceselector::{
+        Color, CursorMode, DeviceRequest, Devices, PROTOCOL_VERSION, PersistMode, Selection,
+        SelectorRequest, Source, SourceTypes, Thumbnail,
+    };
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use zbus::zvariant;
+
+    #[derive(Default)]
+    struct StubSourceSelector {
+        offered: Mutex<Vec<Source>>,
+        thumbnails: Mutex<Vec<Thumbnail>>,
+        cancel: bool,
+    }
+
+    impl SourceSelector for StubSourceSelector {
+        async fn select(&self, request: SelectorRequest) -> Result<Selection, SelectorError> {
+            let sources = Vec::from(request.sources);
+            *self.offered.lock().unwrap() = sources.clone();
+            *self.thumbnails.lock().unwrap() = request.thumbnails;
+            if self.cancel {
+                return Err(SelectorError::Cancelled);
+            }
+            Ok(Selection {
+                sources: sources
+                    .into_iter()
+                    .last()
+                    .into_iter()
+                    .collect::<Vec<_>>()
+                    .into(),
+                cursor_mode: request.cursor_mode,
+                persist_mode: PersistMode::None,
+            })
+        }
+
+        async fn pick_color(&self, _image_path: &str) -> Result<Color, SelectorError> {
+            Err(SelectorError::Cancelled)
+        }
+
+        async fn confirm_devices(&self, request: DeviceRequest) -> Result<Devices, SelectorError> {
+            Ok(request.devices)
+        }
+    }
+
+    struct StubWindow {
+        windows: Vec<(u64, &'static str, Geometry)>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[zbus::interface(name = "org.cinnamon.Muffin.Window")]
+    impl StubWindow {
+        fn list_windows(&self) -> Vec<HashMap<String, zvariant::OwnedValue>> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.windows
+                .iter()
+                .map(|&(id, title, (x, y, width, height))| {
+                    HashMap::from([
+                        ("id".into(), zvariant::OwnedValue::from(id)),
+                        ("title".into(), zvariant::Str::from(title).into()),
+                        ("x".into(), x.into()),
+                        ("y".into(), y.into()),
+                        ("width".into(), width.into()),
+                        ("height".into(), height.into()),
+                    ])
+                })
+                .collect()
+        }
+    }
+
+    struct StubScreenshot {
+        png: Option<Vec<u8>>,
+        areas: Arc<Mutex<Vec<Geometry>>>,
+    }
+
+    #[zbus::interface(name = "org.Cinnamon")]
+    impl StubScreenshot {
+        #[allow(clippy::too_many_arguments)]
+        fn screenshot_area(
+            &self,
+            _include_cursor: bool,
+            x: i32,
+            y: i32,
+            width: i32,
+            height: i32,
+            _flash: bool,
+            filename: String,
+        ) -> zbus::fdo::Result<()> {
+            self.areas.lock().unwrap().push((x, y, width, height));
+            let Some(png) = &self.png else {
+                return Err(zbus::fdo::Error::Failed("capture failed".into()));
+            };
+            std::fs::write(filename, png).map_err(|err| zbus::fdo::Error::Failed(err.to_string()))
+        }
+    }
+
+    async fn connect(
+        serve: impl FnOnce(
+            zbus::connection::Builder<'static>,
+        ) -> zbus::Result<zbus::connection::Builder<'static>>,
+    ) -> (zbus::Connection, zbus::Connection) {
+        let (server, client) = tokio::net::UnixStream::pair().unwrap();
+        let server = zbus::connection::Builder::unix_stream(server)
+            .server(zbus::Guid::generate())
+            .unwrap()
+            .p2p();
+        let server = serve(server).unwrap().build();
+        let client = zbus::connection::Builder::unix_stream(client).p2p().build();
+        let (server, client) = tokio::join!(server, client);
+        (server.unwrap(), client.unwrap())
+    }
+
+    fn fixed_png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbImage::from_pixel(width, height, image::Rgb([32, 64, 128]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        png.into_inner()
+    }
+
+    fn thumbnail_path(name: &str) -> String {
+        format!(
+            "{}/xdg-desktop-portal-screencast-test-{}-{name}.png",
+            std::env::temp_dir().display(),
+            std::process::id()
+        )
+    }
+
+    #[tokio::test]
+    async fn monitor_capture_is_scaled_down() {
+        let (_server, client) = connect(|x| {
+            x.serve_at(
+                "/org/Cinnamon",
+                StubScreenshot {
+                    png: Some(fixed_png(1920, 1080)),
+                    areas: Default::default(),
+                },
+            )
+        })
+        .await;
+        let screenshot_proxy = dbus_proxy::cinnamon::Screenshot::new(&client)
+            .await
+            .unwrap();
+        let path = thumbnail_path("scaled");
+        take_thumbnail(&screenshot_proxy, (0, 0, 1920, 1080), &path)
+            .await
+            .unwrap();
+        let thumbnail = image::open(&path).unwrap();
+        _ = std::fs::remove_file(&path);
+        assert_eq!((thumbnail.width(), thumbnail.height()), THUMBNAIL_SIZE);
+        assert!(!std::path::Path::new(&format!("{path}.capture.png")).exists());
+    }
+
+    #[tokio::test]
+    async fn aspect_ratio_is_kept() {
+        let (_server, client) = connect(|x| {
+            x.serve_at(
+                "/org/Cinnamon",
+                StubScreenshot {
+                    png: Some(fixed_png(1080, 1920)),
+                    areas: Default::default(),
+                },
+            )
+        })
+        .await;
+        let screenshot_proxy = dbus_proxy::cinnamon::Screenshot::new(&client)
+            .await
+            .unwrap();
+        let path = thumbnail_path("portrait");
+        take_thumbnail(&screenshot_proxy, (0, 0, 1080, 1920), &path)
+            .await
+            .unwrap();
+        let thumbnail = image::open(&path).unwrap();
+        _ = std::fs::remove_file(&path);
+        assert_eq!(thumbnail.height(), THUMBNAIL_SIZE.1);
+        assert!(thumbnail.width() < THUMBNAIL_SIZE.1);
+    }
+
+    #[tokio::test]
+    async fn failed_capture_leaves_no_thumbnail() {
+        let (_server, client) = connect(|x| {
+            x.serve_at(
+                "/org/Cinnamon",
+                StubScreenshot {
+                    png: None,
+                    areas: Default::default(),
+                },
+            )
+        })
+        .await;
+        let screenshot_proxy = dbus_proxy::cinnamon::Screenshot::new(&client)
+            .await
+            .unwrap();
+        let path = thumbnail_path("failed");
+        let result = take_thumbnail(&screenshot_proxy, (0, 0, 1920, 1080), &path).await;
+        assert!(result.is_err());
+        assert!(!std::path::Path::new(&path).exists());
+    }
+
+    #[tokio::test]
+    async fn invalid_capture_leaves_no_files() {
+        let (_server, client) = connect(|x| {
+            x.serve_at(
+                "/org/Cinnamon",
+                StubScreenshot {
+                    png: Some(b"not a png".to_vec()),
+                    areas: Default::default(),
+                },
+            )
+        })
+        .await;
+        let screenshot_proxy = dbus_proxy::cinnamon::Screenshot::new(&client)
+            .await
+            .unwrap();
+        let path = thumbnail_path("invalid");
+        let result = take_thumbnail(&screenshot_proxy, (0, 0, 1920, 1080), &path).await;
+        assert!(result.is_err());
+        assert!(!std::path::Path::new(&path).exists());
+        assert!(!std::path::Path::new(&format!("{path}.capture.png")).exists());
+    }
+
+    fn window_request() -> SelectorRequest {
+        SelectorRequest {
+            version: PROTOCOL_VERSION,
+            app_id: "org.example.App".into(),
+            source_types: SourceTypes {
+                monitor: false,
+                window: true,
+                area: false,
+                virtual_monitor: false,
+            },
+            multiple: false,
+            cursor_modes: vec![CursorMode::Hidden],
+            cursor_mode: CursorMode::Hidden,
+            persist_mode: PersistMode::None,
+            sources: Vec::new().into(),
+            thumbnails: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn windows_are_offered_to_the_source_selector() {
+        let stub_window = StubWindow {
+            windows: vec![(7, "Terminal", (0, 0, 0, 0)), (9, "Terminal", (0, 0, 0, 0))],
+            calls: Default::default(),
+        };
+        let (_server, client) =
+            connect(|x| x.serve_at("/org/cinnamon/Muffin/Window", stub_window)).await;
+        let source_selector = StubSourceSelector::default();
+        let source_catalog = SourceCatalog::new(&client, source_selector).await.unwrap();
+        let selection = source_catalog
+            .select_sources(window_request())
+            .await
+            .unwrap();
+        let offered = source_catalog.source_selector.offered.lock().unwrap();
+        let offered_ids: Vec<_> = offered
+            .iter()
+            .map(|x| match x {
+                Source::Window {
+                    window_id,
+                    window_name,
+                    ..
+                } => (*window_id, window_name.as_str()),
+                _ => panic!("only windows were requested"),
+            })
+            .collect();
+        assert_eq!(offered_ids, [(7, "Terminal"), (9, "Terminal")]);
+        assert!(matches!(
+            Vec::from(selection.sources).as_slice(),
+            [Source::Window { window_id: 9, .. }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn cancelled_selection_is_reported_as_cancelled() {
+        let stub_window = StubWindow {
+            windows: vec![(7, "Terminal", (0, 0, 0, 0))],
+            calls: Default::default(),
+        };
+        let (_server, client) =
+            connect(|x| x.serve_at("/org/cinnamon/Muffin/Window", stub_window)).await;
+        let source_selector = StubSourceSelector {
+            cancel: true,
+            ..Default::default()
+        };
+        let source_catalog = SourceCatalog::new(&client, source_selector).await.unwrap();
+        let result = source_catalog.select_sources(window_request()).await;
+        assert!(matches!(result, Err(Error::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn window_thumbnails_capture_each_window_geometry() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let areas = Arc::new(Mutex::new(Vec::new()));
+        let stub_window = StubWindow {
+            windows: vec![
+                (7, "Terminal", (0, 0, 800, 600)),
+                (9, "Terminal", (800, 0, 640, 480)),
+            ],
+            calls: calls.clone(),
+        };
+        let stub_screenshot = StubScreenshot {
+            png: Some(fixed_png(800, 600)),
+            areas: areas.clone(),
+        };
+        let (_server, client) = connect(|x| {
+            x.serve_at("/org/cinnamon/Muffin/Window", stub_window)?
+                .serve_at("/org/Cinnamon", stub_screenshot)
+        })
+        .await;
+        let source_catalog = SourceCatalog::new(&client, StubSourceSelector::default())
+            .await
+            .unwrap();
+        source_catalog
+            .select_sources(window_request())
+            .await
+            .unwrap();
+        let thumbnails = source_catalog.source_selector.thumbnails.lock().unwrap();
+        let thumbnail_ids: Vec<_> = thumbnails
+            .iter()
+            .map(|x| match x.source {
+                Source::Window { window_id, .. } => window_id,
+                _ => panic!("only windows were requested"),
+            })
+            .collect();
+        assert_eq!(thumbnail_ids, [7, 9]);
+        assert!(
+            thumbnails
+                .iter()
+                .all(|x| !std::path::Path::new(&x.path).exists())
+        );
+        let mut areas = areas.lock().unwrap().clone();
+        areas.sort();
+        assert_eq!(areas, [(0, 0, 800, 600), (800, 0, 640, 480)]);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn window_geometry_is_looked_up_by_id() {
+        let stub_window = StubWindow {
+            windows: vec![
+                (7, "Terminal", (0, 0, 800, 600)),
+                (9, "Terminal", (800, 0, 640, 480)),
+            ],
+            calls: Default::default(),
+        };
+        let (_server, client) =
+            connect(|x| x.serve_at("/org/cinnamon/Muffin/Window", stub_window)).await;
+        let source_catalog = SourceCatalog::new(&client, StubSourceSelector::default())
+            .await
+            .unwrap();
+        let geometry = source_catalog.get_window_geometry(9).await.unwrap();
+        assert_eq!(geometry, Some((800, 0, 640, 480)));
+        let geometry = source_catalog.get_window_geometry(8).await.unwrap();
+        assert_eq!(geometry, None);
+    }
+}
@@ -1,38 +1,96 @@
 use crate::{dbus_proxy, portal_impl};
 use futures_util::StreamExt;
-use std::collections::HashSet;
+use std::{collections::HashSet, time::Duration};
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 pub async fn setup(
     connection: &zbus::Connection,
     screencast_ctx: portal_impl::ScreenCast,
 ) -> zbus::Result<()> {
     let portal_handlers = dbus_proxy::cinnamon::PortalHandlers::new(connection).await?;
-    let mut stream = portal_handlers.receive_running_apps_changed().await?;
-
-    tokio::spawn(async move {
-        let mut last_apps = HashSet::new();
-        while stream.next().await.is_some() {
-            let result = portal_handlers.get_app_states().await;
-            match result {
-                Ok(apps) => {
-                    let apps: HashSet<_> = apps
-                        .keys()
-                        .map(|x| x.strip_suffix(".desktop").unwrap_or(x).to_owned())
-                        .filter(|x| !x.is_empty())
-                        .collect();
-                    for app_id in last_apps.difference(&apps) {
-                        screencast_ctx.on_app_closed(app_id).await;
+    let mut watcher = RunningAppWatcher {
+        portal_handlers,
+        screencast_ctx,
+        last_apps: HashSet::new(),
+        backoff: MIN_BACKOFF,
+    };
+
+    tokio::spawn(async move { watcher.supervise().await });
+
+    Ok(())
+}
+
+struct RunningAppWatcher {
+    portal_handlers: dbus_proxy::cinnamon::PortalHandlers<'static>,
+    screencast_ctx: portal_impl::ScreenCast,
+    last_apps: HashSet<String>,
+    backoff: Duration,
+}
+
+impl RunningAppWatcher {
+    async fn supervise(&mut self) -> ! {
+        loop {
+            match self.watch().await {
+                Ok(()) => {
+                    log::warn!("org.cinnamon.PortalHandlers - RunningAppsChanged(): unavailable")
+                }
+                Err(err) => log::warn!("org.cinnamon.PortalHandlers - RunningAppsChanged(): {err}"),
+            }
+            log::warn!(
+                "Polling running apps every {POLL_INTERVAL:?}, resubscribing in {:?}",
+                self.backoff
+            );
+            let deadline = tokio::time::Instant::now() + self.backoff;
+            while tokio::time::Instant::now() < deadline {
+                if let Err(err) = self.refresh().await {
+                    log::debug!("org.cinnamon.PortalHandlers - GetAppStates(): {err}");
+                }
+                tokio::time::sleep_until(deadline.min(tokio::time::Instant::now() + POLL_INTERVAL))
+                    .await;
+            }
+            self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    async fn watch(&mut self) -> zbus::Result<()> {
+        let mut running_apps_changed = self.portal_handlers.receive_running_apps_changed().await?;
+        let mut owner_changed = self.portal_handlers.inner().receive_owner_changed().await?;
+        self.refresh().await?;
+        log::info!("Watching org.cinnamon.PortalHandlers for running apps");
+        self.backoff = MIN_BACKOFF;
+        loop {
+            tokio::select! {
+                signal = running_apps_changed.next() => {
+                    if signal.is_none() {
+                        return Ok(());
+                    }
+                    if let Err(err) = self.refresh().await {
+                        log::error!("org.cinnamon.PortalHandlers - GetAppStates(): {err}");
+                    }
+                }
+                owner = owner_changed.next() => {
+                    if !matches!(owner, Some(Some(_))) {
+                        return Ok(());
                     }
-                    last_apps = apps;
                 }
-                Err(err) => log::error!("org.cinnamon.PortalHandlers - GetAppStates(): {err}"),
             }
         }
-        // Something went wrong in the compositor?
-        panic!(
-            "org.cinnamon.PortalHandlers - RunningAppsChanged(): The signal stream ended unexpectedly"
-        );
-    });
+    }
 
-    Ok(())
+    async fn refresh(&mut self) -> zbus::Result<()> {
+        let apps = self.portal_handlers.get_app_states().await?;
+        let apps: HashSet<_> = apps
+            .keys()
+            .map(|x| x.strip_suffix(".desktop").unwrap_or(x).to_owned())
+            .filter(|x| !x.is_empty())
+            .collect();
+        for app_id in self.last_apps.difference(&apps) {
+            self.screencast_ctx.on_app_closed(app_id).await;
+        }
+        self.last_apps = apps;
+        Ok(())
+    }
 }
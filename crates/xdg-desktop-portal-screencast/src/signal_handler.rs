@@ -0,0 +1,34 @@
+use crate::config;
+use tokio::signal::unix::{Signal, SignalKind, signal};
+
+pub struct SignalHandler {
+    sigterm: Signal,
+    sigint: Signal,
+    sighup: Signal,
+}
+
+impl SignalHandler {
+    pub fn setup() -> std::io::Result<Self> {
+        Ok(Self {
+            sigterm: signal(SignalKind::terminate())?,
+            sigint: signal(SignalKind::interrupt())?,
+            sighup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    pub async fn wait_for_shutdown(&mut self) {
+        loop {
+            let name = tokio::select! {
+                _ = self.sigterm.recv() => "SIGTERM",
+                _ = self.sigint.recv() => "SIGINT",
+                _ = self.sighup.recv() => {
+                    log::info!("SIGHUP received, reloading the configuration");
+                    config::reload();
+                    continue;
+                }
+            };
+            log::info!("{name} received, shutting down");
+            return;
+        }
+    }
+}